@@ -0,0 +1,264 @@
+//! Checks every `assets/tl/**/*.tl.ron` file the same way the game loads them.
+//!
+//! Usage: `kairoi-tl-lint [ASSET_DIR]`, where `ASSET_DIR` defaults to `assets` in the current
+//! directory.
+
+use bevy::{
+	asset::{AssetMetaCheck, AssetPath},
+	prelude::*,
+	reflect::TypeRegistry,
+	utils::HashMap,
+};
+use game_lib::{
+	data::tl::{MomentRef, Timeline, TimelineDeserializer, Trigger},
+	happens::{ModifyTimeline, SpawnPortal, SpawnTrigger},
+	HeadlessDataPlugin, ASSET_SERVER, TYPE_REGISTRY,
+};
+use ron::error::Position;
+use serde::de::DeserializeSeed;
+use std::{
+	fmt::{self, Display, Formatter},
+	path::{Path, PathBuf},
+	process::ExitCode,
+};
+
+fn main() -> ExitCode {
+	let asset_dir = match std::env::args().nth(1) {
+		Some(dir) => PathBuf::from(dir),
+		None => match std::env::current_dir() {
+			Ok(dir) => dir.join("assets"),
+			Err(e) => {
+				eprintln!("failed to get the current directory: {e}");
+				return ExitCode::FAILURE;
+			}
+		},
+	};
+
+	let mut app = App::new();
+	app.insert_resource(AssetMetaCheck::Never).add_plugins((
+		MinimalPlugins,
+		AssetPlugin {
+			file_path: asset_dir.to_string_lossy().into_owned(),
+			mode: AssetMode::Unprocessed,
+			..default()
+		},
+		HeadlessDataPlugin,
+	));
+	TYPE_REGISTRY
+		.set(app.world.resource::<AppTypeRegistry>().0.clone())
+		.expect("AppTypeRegistry already set");
+	ASSET_SERVER
+		.set(app.world.resource::<AssetServer>().clone())
+		.expect("AssetServer already set");
+	app.finish();
+	app.cleanup();
+
+	let mut files = Vec::new();
+	if let Err(e) = find_timelines(&asset_dir.join("tl"), &mut files) {
+		eprintln!("failed to read {}: {e}", asset_dir.join("tl").display());
+		return ExitCode::FAILURE;
+	}
+	files.sort();
+
+	let srv = app.world.resource::<AssetServer>();
+	let sources = files
+		.iter()
+		.map(|file| {
+			let path = asset_path(&asset_dir, file);
+			// Makes sure `branch_from`/`merge_into` can resolve to any existing file.
+			let _ = srv.load::<Timeline>(path.clone());
+			(path, std::fs::read_to_string(file))
+		})
+		.collect::<Vec<_>>();
+
+	let registry = app.world.resource::<AppTypeRegistry>().read();
+	let mut lints = Vec::new();
+	let mut timelines = HashMap::new();
+	for (path, src) in &sources {
+		let src = match src {
+			Ok(src) => src,
+			Err(e) => {
+				lints.push(Lint::new(path, None, format!("failed to read file: {e}")));
+				continue;
+			}
+		};
+		match parse(src, &registry, srv) {
+			Ok(tl) => {
+				for (link, t_path) in &tl.unresolved {
+					lints.push(Lint::new(
+						path,
+						find(src, t_path.0.path().to_string_lossy().as_ref()),
+						format!("`{link}` refers to missing timeline `{}`", t_path.0),
+					));
+				}
+				timelines.insert(path.clone(), tl);
+			}
+			Err(e) => lints.push(Lint::new(path, Some(e.position), e.code.to_string())),
+		}
+	}
+
+	for (path, src) in &sources {
+		let (Ok(src), Some(tl)) = (src, timelines.get(path)) else {
+			continue;
+		};
+		for (lt, moment) in tl.moments.iter() {
+			for happenings in &moment.happenings {
+				for action in &happenings.actions {
+					check_action(action.as_reflect(), &timelines, &mut |msg, needle| {
+						lints.push(Lint::new(
+							path,
+							needle.and_then(|needle| find(src, &needle)),
+							format!("{msg} (in moment at {lt})"),
+						))
+					});
+				}
+			}
+		}
+	}
+
+	for lint in &lints {
+		eprintln!("{lint}");
+	}
+	if lints.is_empty() {
+		println!("{} timelines OK", sources.len());
+		ExitCode::SUCCESS
+	} else {
+		eprintln!("{} problems in {} timelines", lints.len(), sources.len());
+		ExitCode::FAILURE
+	}
+}
+
+fn parse(
+	src: &str,
+	registry: &TypeRegistry,
+	asset_server: &AssetServer,
+) -> Result<Timeline, ron::error::SpannedError> {
+	let mut ron_de = ron::de::Deserializer::from_str(src)?;
+	TimelineDeserializer {
+		registry,
+		asset_server,
+	}
+	.deserialize(&mut ron_de)
+	.map_err(|e| ron_de.span_error(e))
+}
+
+/// Reports problems with any `ModifyTimeline` or `SpawnPortal`, including ones nested in spawned
+/// triggers.
+fn check_action(
+	action: &dyn Reflect,
+	timelines: &HashMap<AssetPath<'static>, Timeline>,
+	report: &mut dyn FnMut(String, Option<String>),
+) {
+	if let Some(ModifyTimeline(commands)) = action.downcast_ref::<ModifyTimeline>() {
+		for command in commands {
+			let Some(target) = timelines.get(&command.path) else {
+				report(
+					format!(
+						"`ModifyTimeline` refers to missing timeline `{}`",
+						command.path
+					),
+					Some(command.path.to_string()),
+				);
+				continue;
+			};
+			for update in &command.updates {
+				if target.get_moment(&update.moment).is_some() {
+					continue;
+				}
+				let needle = match &update.moment {
+					MomentRef::At(t) => format!("{t}"),
+					MomentRef::Labelled(label) => format!("\"{label}\""),
+				};
+				report(
+					format!(
+						"`ModifyTimeline` refers to missing moment {:?} in `{}`",
+						update.moment, command.path
+					),
+					Some(needle),
+				);
+			}
+		}
+	} else if let Some(spawn) = action.downcast_ref::<SpawnPortal>() {
+		let path = &spawn.portal.path;
+		for t_path in [&path.from, &path.to] {
+			if !timelines.contains_key(&t_path.0) {
+				report(
+					format!("`SpawnPortal` refers to missing timeline `{}`", t_path.0),
+					Some(t_path.0.to_string()),
+				);
+			}
+		}
+	} else if let Some(spawn) = action.downcast_ref::<SpawnTrigger>() {
+		check_trigger(&spawn.trigger, timelines, report);
+	} else if let Some(trigger) = action.downcast_ref::<Trigger>() {
+		check_trigger(trigger, timelines, report);
+	}
+}
+
+fn check_trigger(
+	trigger: &Trigger,
+	timelines: &HashMap<AssetPath<'static>, Timeline>,
+	report: &mut dyn FnMut(String, Option<String>),
+) {
+	for cause in trigger.causes.iter() {
+		check_action(cause.as_reflect(), timelines, report);
+	}
+}
+
+fn find_timelines(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			find_timelines(&path, out)?;
+		} else if path.to_string_lossy().ends_with(".tl.ron") {
+			out.push(path);
+		}
+	}
+	Ok(())
+}
+
+fn asset_path(asset_dir: &Path, file: &Path) -> AssetPath<'static> {
+	let relative = file.strip_prefix(asset_dir).unwrap_or(file);
+	let relative = relative
+		.components()
+		.map(|part| part.as_os_str().to_string_lossy())
+		.collect::<Vec<_>>()
+		.join("/");
+	AssetPath::from(relative)
+}
+
+/// Position of the first occurrence of `needle` in `src`.
+fn find(src: &str, needle: &str) -> Option<Position> {
+	let offset = src.find(needle)?;
+	let before = &src[..offset];
+	let line = before.matches('\n').count() + 1;
+	let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+	Some(Position { line, col })
+}
+
+struct Lint {
+	path: AssetPath<'static>,
+	position: Option<Position>,
+	message: String,
+}
+
+impl Lint {
+	fn new(path: &AssetPath<'static>, position: Option<Position>, message: String) -> Self {
+		Self {
+			path: path.clone(),
+			position,
+			message,
+		}
+	}
+}
+
+impl Display for Lint {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.position {
+			Some(Position { line, col }) => {
+				write!(f, "{}:{line}:{col}: {}", self.path, self.message)
+			}
+			None => write!(f, "{}: {}", self.path, self.message),
+		}
+	}
+}
@@ -0,0 +1,176 @@
+//! [`Condition`]s that gate [`Happenings`](crate::data::tl::Happenings) on the state of the world.
+
+use crate::{
+	data::{
+		flags::{FlagValue, WorldFlags},
+		phys::{isometry, ColliderShape},
+		tl::{Condition, ReflectCondition, TimeLoop, Timeline},
+		Str,
+	},
+	player::player_entity::Root,
+};
+use bevy::{
+	asset::{AssetPath, UntypedAssetId},
+	prelude::*,
+};
+use bevy_xpbd_3d::parry::{query::PointQuery, shape::SharedShape};
+use serde::{Deserialize, Serialize};
+use sond_bevy_enum_components::WithVariant;
+use std::cmp::Ordering;
+
+pub struct ConditionsPlugin;
+
+impl Plugin for ConditionsPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_type::<EntityExists>()
+			.register_type::<PlayerInside>()
+			.register_type::<InTimeline>()
+			.register_type::<FlagSet>()
+			.register_type::<FlagCompare>()
+			.register_type::<CompareOp>();
+	}
+}
+
+/// Holds if any entity has the given `Name`.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Condition, Serialize, Deserialize)]
+#[type_path = "conditions"]
+#[serde(transparent)]
+pub struct EntityExists(pub Name);
+
+impl Condition for EntityExists {
+	fn check(&self, world: &mut World) -> bool {
+		world
+			.query::<&Name>()
+			.iter(world)
+			.any(|name| *name == self.0)
+	}
+
+	fn clone_condition(&self) -> Box<dyn Condition> {
+		Box::new(self.clone())
+	}
+}
+
+/// Holds if the player's origin is inside `shape`, placed at `transform`.
+#[derive(Debug, Default, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Condition, Serialize, Deserialize)]
+#[type_path = "conditions"]
+#[serde(default)]
+pub struct PlayerInside {
+	pub shape: ColliderShape,
+	pub transform: Transform,
+}
+
+impl Condition for PlayerInside {
+	fn check(&self, world: &mut World) -> bool {
+		let shape = match SharedShape::try_from(self.shape.clone()) {
+			Ok(shape) => shape,
+			Err(e) => {
+				error!("Invalid `PlayerInside` shape: {e}");
+				return false;
+			}
+		};
+		let mut q = world.query_filtered::<&GlobalTransform, WithVariant<Root>>();
+		let Ok(player) = q.get_single(world) else {
+			return false;
+		};
+		shape.contains_point(&isometry(&self.transform), &player.translation().into())
+	}
+
+	fn clone_condition(&self) -> Box<dyn Condition> {
+		Box::new(self.clone())
+	}
+}
+
+/// Holds if the loop is currently in the given timeline.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Condition, Serialize, Deserialize)]
+#[type_path = "conditions"]
+#[serde(transparent)]
+pub struct InTimeline(pub AssetPath<'static>);
+
+impl Condition for InTimeline {
+	fn check(&self, world: &mut World) -> bool {
+		let curr = world.resource::<TimeLoop>().curr.0;
+		world
+			.resource::<AssetServer>()
+			.get_path_id(self.0.clone())
+			.map(UntypedAssetId::typed::<Timeline>)
+			.is_some_and(|id| id == curr)
+	}
+
+	fn clone_condition(&self) -> Box<dyn Condition> {
+		Box::new(self.clone())
+	}
+}
+
+/// Holds if the [`WorldFlags`] entry is set to anything but `Bool(false)`.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Condition, Serialize, Deserialize)]
+#[type_path = "conditions"]
+#[serde(transparent)]
+pub struct FlagSet(pub Str);
+
+impl Condition for FlagSet {
+	fn check(&self, world: &mut World) -> bool {
+		world
+			.get_resource::<WorldFlags>()
+			.and_then(|flags| flags.value(&self.0))
+			.is_some_and(FlagValue::is_truthy)
+	}
+
+	fn clone_condition(&self) -> Box<dyn Condition> {
+		Box::new(self.clone())
+	}
+}
+
+/// Holds if the [`WorldFlags`] entry is set to the same kind of value and `flag <op> value`.
+///
+/// E.g. `(flag: "lever_flipped_at", op: Lt, value: Time("10s"))`.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Condition, Serialize, Deserialize)]
+#[type_path = "conditions"]
+pub struct FlagCompare {
+	pub flag: Str,
+	pub op: CompareOp,
+	pub value: FlagValue,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+#[type_path = "conditions"]
+pub enum CompareOp {
+	Lt,
+	Le,
+	Eq,
+	Ne,
+	Ge,
+	Gt,
+}
+
+impl CompareOp {
+	pub fn holds(self, ord: Ordering) -> bool {
+		match self {
+			CompareOp::Lt => ord.is_lt(),
+			CompareOp::Le => ord.is_le(),
+			CompareOp::Eq => ord.is_eq(),
+			CompareOp::Ne => ord.is_ne(),
+			CompareOp::Ge => ord.is_ge(),
+			CompareOp::Gt => ord.is_gt(),
+		}
+	}
+}
+
+impl Condition for FlagCompare {
+	fn check(&self, world: &mut World) -> bool {
+		world
+			.get_resource::<WorldFlags>()
+			.and_then(|flags| flags.value(&self.flag))
+			.and_then(|value| value.compare(self.value))
+			.is_some_and(|ord| self.op.holds(ord))
+	}
+
+	fn clone_condition(&self) -> Box<dyn Condition> {
+		Box::new(self.clone())
+	}
+}
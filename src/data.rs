@@ -1,4 +1,3 @@
-use crate::asset_server;
 use bevy::{
 	asset::AssetPath,
 	ecs::system::SystemId,
@@ -22,10 +21,16 @@ use std::{
 	marker::PhantomData,
 };
 
+pub mod anim;
+pub mod area;
 pub mod cam;
+pub mod dlg;
+pub mod flags;
+pub mod pbr;
 pub mod phys;
 pub mod sprites;
 pub mod tl;
+pub mod tuning;
 pub mod ui;
 
 pub struct DataPlugin;
@@ -34,6 +39,7 @@ impl Plugin for DataPlugin {
 	fn build(&self, app: &mut App) {
 		app.register_type::<LoadAsset<Image>>()
 			.register_type::<LoadSprite3d>()
+			.register_type::<cam::AvoidOccludingPlayer>()
 			.add_systems(
 				Last,
 				(
@@ -42,7 +48,16 @@ impl Plugin for DataPlugin {
 					set_atlas_3d_meshes,
 				),
 			)
-			.add_plugins((tl::TimeDataPlugin, phys::PhysDataPlugin));
+			.add_plugins((
+				tl::TimeDataPlugin,
+				phys::PhysDataPlugin,
+				flags::FlagDataPlugin,
+				tuning::TuningDataPlugin,
+				dlg::DialogueDataPlugin,
+				area::AreaDataPlugin,
+				pbr::PbrDataPlugin,
+				anim::AnimDataPlugin,
+			));
 	}
 }
 
@@ -234,6 +249,21 @@ pub mod entity_path_str {
 	}
 }
 
+/// Human-readable [`Duration`](std::time::Duration)s, e.g. `"1s 500ms"`, using [humantime].
+pub mod duration_str {
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+	use std::time::Duration;
+
+	pub fn serialize<S: Serializer>(dur: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&humantime::format_duration(*dur).to_string())
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+		let s = <&'de str as Deserialize<'de>>::deserialize(deserializer)?;
+		humantime::parse_duration(s).map_err(|e| Error::custom(format_args!("{e}")))
+	}
+}
+
 #[derive(Resource, Debug, Deref, DerefMut)]
 pub struct SystemRegistry(#[deref] pub HashMap<Str, SystemId>);
 
@@ -243,6 +273,32 @@ impl FromWorld for SystemRegistry {
 	}
 }
 
+/// Lets data files run one-shot systems by name with [`RunSystem`](crate::happens::RunSystem).
+pub trait RegisterNamedSystem {
+	fn register_named_system<M>(
+		&mut self,
+		name: &str,
+		system: impl IntoSystem<(), (), M> + 'static,
+	) -> &mut Self;
+}
+
+impl RegisterNamedSystem for App {
+	fn register_named_system<M>(
+		&mut self,
+		name: &str,
+		system: impl IntoSystem<(), (), M> + 'static,
+	) -> &mut Self {
+		let id = self.world.register_system(system);
+		let mut registry = self
+			.world
+			.get_resource_or_insert_with(|| SystemRegistry(default()));
+		if let Some(old) = registry.insert(name.into(), id) {
+			warn!("Replaced system {old:?} named {name}");
+		}
+		self
+	}
+}
+
 #[derive(Component, Reflect, Clone, Debug, Deref, DerefMut, Serialize, Deserialize)]
 #[reflect(Component, Serialize, Deserialize)]
 pub struct LoadAsset<T: Asset>(
@@ -271,8 +327,8 @@ pub struct InlineAsset<T: Asset + Reflect + FromReflect> {
 }
 
 impl<T: Asset + Serialize + DeserializeOwned + Reflect + FromReflect> InlineAsset<T> {
-	pub fn into_handle(self) -> Handle<T> {
-		asset_server().add(self.value)
+	pub fn into_handle(self, srv: &AssetServer) -> Handle<T> {
+		srv.add(self.value)
 	}
 }
 
@@ -341,6 +397,15 @@ impl Default for LoadStdMat {
 
 impl LoadStdMat {
 	pub fn load_using(self, server: &AssetServer) -> StandardMaterial {
+		self.load_with(|path| server.load(path))
+	}
+
+	/// Like [`load_using`](Self::load_using), but loading textures with `load_image`, e.g. from
+	/// an [`AssetLoader`](bevy::asset::AssetLoader)'s `LoadContext`.
+	pub fn load_with(
+		self,
+		mut load_image: impl FnMut(AssetPath<'static>) -> Handle<Image>,
+	) -> StandardMaterial {
 		let Self {
 			base_color_texture,
 			base_color,
@@ -356,7 +421,7 @@ impl LoadStdMat {
 		} = self;
 
 		StandardMaterial {
-			base_color_texture: base_color_texture.map(|path| server.load(path)),
+			base_color_texture: base_color_texture.map(&mut load_image),
 			base_color,
 			alpha_mode: alpha_mode.into(),
 			depth_bias,
@@ -369,7 +434,7 @@ impl LoadStdMat {
 			unlit,
 			double_sided,
 			emissive,
-			emissive_texture: emissive_texture.map(|path| server.load(path)),
+			emissive_texture: emissive_texture.map(&mut load_image),
 			perceptual_roughness,
 			// metallic,
 			// metallic_roughness_texture,
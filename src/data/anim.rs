@@ -0,0 +1,147 @@
+//! [`AnimationClip`]s for scene files.
+
+use super::{entity_path_str, Str};
+use bevy::{
+	animation::EntityPath,
+	asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, BoxedFuture, LoadContext},
+	prelude::*,
+	scene::SceneLoaderError,
+	utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct AnimDataPlugin;
+
+impl Plugin for AnimDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_asset_loader(AnimationClipLoader)
+			.register_type::<LoadAnimations>()
+			.add_systems(Last, replace_animation_paths);
+	}
+}
+
+/// An `.anim.ron` file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnimationClipDef {
+	pub curves: Vec<CurveDef>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CurveDef {
+	/// [`Name`]s from the entity with the [`AnimationPlayer`] to the animated one, e.g.
+	/// `"Walls.NorthWall"`.
+	#[serde(with = "entity_path_str")]
+	pub path: EntityPath,
+	/// Seconds from the start of the clip.
+	pub keyframe_timestamps: Vec<f32>,
+	pub keyframes: KeyframesDef,
+	#[serde(default)]
+	pub interpolation: InterpolationDef,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum KeyframesDef {
+	Translation(Vec<Vec3>),
+	Rotation(Vec<Quat>),
+	Scale(Vec<Vec3>),
+	Weights(Vec<f32>),
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum InterpolationDef {
+	#[default]
+	Linear,
+	Step,
+	CubicSpline,
+}
+
+impl From<KeyframesDef> for Keyframes {
+	fn from(value: KeyframesDef) -> Self {
+		match value {
+			KeyframesDef::Translation(frames) => Keyframes::Translation(frames),
+			KeyframesDef::Rotation(frames) => Keyframes::Rotation(frames),
+			KeyframesDef::Scale(frames) => Keyframes::Scale(frames),
+			KeyframesDef::Weights(frames) => Keyframes::Weights(frames),
+		}
+	}
+}
+
+impl From<InterpolationDef> for Interpolation {
+	fn from(value: InterpolationDef) -> Self {
+		match value {
+			InterpolationDef::Linear => Interpolation::Linear,
+			InterpolationDef::Step => Interpolation::Step,
+			InterpolationDef::CubicSpline => Interpolation::CubicSpline,
+		}
+	}
+}
+
+impl From<AnimationClipDef> for AnimationClip {
+	fn from(value: AnimationClipDef) -> Self {
+		let mut clip = AnimationClip::default();
+		for curve in value.curves {
+			clip.add_curve_to_path(
+				curve.path,
+				VariableCurve {
+					keyframe_timestamps: curve.keyframe_timestamps,
+					keyframes: curve.keyframes.into(),
+					interpolation: curve.interpolation.into(),
+				},
+			);
+		}
+		clip
+	}
+}
+
+pub struct AnimationClipLoader;
+
+impl AssetLoader for AnimationClipLoader {
+	type Asset = AnimationClip;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			Ok(ron::de::from_bytes::<AnimationClipDef>(&bytes)?.into())
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["anim.ron"]
+	}
+}
+
+/// Animations an entity can play, by name. Replaced with [`Animations`] and an
+/// [`AnimationPlayer`].
+#[derive(Component, Reflect, Clone, Debug, Default, Deref, DerefMut, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct LoadAnimations(pub HashMap<Str, AssetPath<'static>>);
+
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+pub struct Animations(pub HashMap<Str, Handle<AnimationClip>>);
+
+pub fn replace_animation_paths(
+	mut cmds: Commands,
+	q: Query<(Entity, &LoadAnimations, Has<AnimationPlayer>)>,
+	srv: Res<AssetServer>,
+) {
+	for (id, LoadAnimations(paths), has_player) in &q {
+		let clips = paths
+			.iter()
+			.map(|(name, path)| (*name, srv.load(path.clone())))
+			.collect();
+		let mut cmds = cmds.entity(id);
+		cmds.insert(Animations(clips)).remove::<LoadAnimations>();
+		if !has_player {
+			cmds.insert(AnimationPlayer::default());
+		}
+	}
+}
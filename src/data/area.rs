@@ -0,0 +1,106 @@
+//! Areas of the world, each a scene and a timeline, streamed in and out as the player moves.
+
+use super::Str;
+use bevy::{
+	asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, BoxedFuture, LoadContext},
+	prelude::*,
+	scene::SceneLoaderError,
+	utils::HashMap,
+};
+use bevy_asset_loader::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct AreaDataPlugin;
+
+impl Plugin for AreaDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_asset::<AreaIndex>()
+			.register_asset_loader(AreaIndexLoader)
+			.register_type::<AreaId>()
+			.init_resource::<ActiveArea>();
+	}
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct Areas {
+	#[asset(path = "world.areas.ron")]
+	pub index: Handle<AreaIndex>,
+}
+
+/// Every area in the game.
+#[derive(Asset, TypePath, Clone, Debug, Serialize, Deserialize)]
+pub struct AreaIndex {
+	pub areas: HashMap<Str, Area>,
+	/// How close the player has to get to an area for it to be loaded.
+	#[serde(default = "_load_margin")]
+	pub load_margin: f32,
+	/// How far the player has to get from an area for it to be unloaded. Larger than
+	/// `load_margin` so walking along the edge doesn't keep loading and unloading it.
+	#[serde(default = "_unload_margin")]
+	pub unload_margin: f32,
+}
+
+fn _load_margin() -> f32 {
+	8.0
+}
+
+fn _unload_margin() -> f32 {
+	16.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Area {
+	/// `.scn.ron` spawned while the area is loaded.
+	pub scene: AssetPath<'static>,
+	/// Timeline the loop switches to on entering the area, unless it is already in one branched
+	/// from it.
+	pub timeline: AssetPath<'static>,
+	/// Corners of the area on the ground plane.
+	pub min: Vec2,
+	pub max: Vec2,
+}
+
+impl Area {
+	/// How far `pos` is outside the area, or 0 if it is inside.
+	pub fn distance(&self, pos: Vec2) -> f32 {
+		(self.min - pos)
+			.max(pos - self.max)
+			.max(Vec2::ZERO)
+			.length()
+	}
+}
+
+/// The area an entity belongs to. It is unloaded along with the area, and only reset by
+/// [`reset_world`](crate::happens::reset_world) while the area is active.
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct AreaId(pub Str);
+
+/// The area the player is in, or was in last if they are between areas.
+#[derive(Resource, Copy, Clone, Debug, Default, Deref, DerefMut)]
+pub struct ActiveArea(pub Option<Str>);
+
+pub struct AreaIndexLoader;
+
+impl AssetLoader for AreaIndexLoader {
+	type Asset = AreaIndex;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			Ok(ron::de::from_bytes::<AreaIndex>(&bytes)?)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["areas.ron"]
+	}
+}
@@ -25,7 +25,10 @@ impl AvoidOccludingPlayer {
 	pub fn shape(&self) -> RwLockReadGuard<Option<SharedShape>> {
 		if let Some(desc) = self.if_in_area.as_ref().cloned() {
 			if self.area_shape.read().is_none() {
-				*self.area_shape.write() = Some(desc.into());
+				match SharedShape::try_from(desc) {
+					Ok(shape) => *self.area_shape.write() = Some(shape),
+					Err(e) => error!("Invalid `if_in_area` shape: {e}"),
+				}
 			}
 		}
 		self.area_shape.read()
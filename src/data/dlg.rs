@@ -0,0 +1,134 @@
+//! Dialogue shown by [`ShowDialogue`](crate::happens::ShowDialogue).
+
+use super::{
+	tl::{do_list_serde, DoList},
+	Str,
+};
+use bevy::{
+	asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, BoxedFuture, LoadContext},
+	prelude::*,
+	scene::SceneLoaderError,
+	utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct DialogueDataPlugin;
+
+impl Plugin for DialogueDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_asset::<Dialogue>()
+			.register_asset_loader(DialogueLoader)
+			.init_resource::<SeenDialogue>();
+	}
+}
+
+/// A `.dlg.ron` file. Lines play in order unless a `goto` or a [`Choice`] says otherwise.
+#[derive(Asset, TypePath, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Dialogue {
+	pub lines: Vec<Line>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Line {
+	/// Lets `goto`s jump here, and keeps this line marked as seen if lines before it change.
+	pub label: Option<Str>,
+	pub speaker: Option<String>,
+	pub text: String,
+	/// Only show this line in these timelines. Shown in every timeline if empty.
+	pub timelines: Vec<AssetPath<'static>>,
+	/// Offered once the line has been read. The dialogue waits until one is picked.
+	pub choices: Vec<Choice>,
+	/// Label of the line to continue from instead of the next one.
+	pub goto: Option<Str>,
+	/// Ends the dialogue after this line.
+	pub end: bool,
+}
+
+impl Line {
+	pub fn shown_in(&self, timeline: Option<&AssetPath>) -> bool {
+		self.timelines.is_empty()
+			|| timeline.map_or(false, |tl| self.timelines.iter().any(|it| it == tl))
+	}
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Choice {
+	pub text: String,
+	#[serde(with = "do_list_serde")]
+	pub causes: DoList,
+	/// Label of the line to continue from. Ends the dialogue if empty.
+	pub goto: Option<Str>,
+}
+
+impl Dialogue {
+	/// The first line at or after `from` that is shown in `timeline`.
+	pub fn next_line(&self, from: usize, timeline: Option<&AssetPath>) -> Option<usize> {
+		(from..self.lines.len()).find(|i| self.lines[*i].shown_in(timeline))
+	}
+
+	/// The line labelled `label`, or the first one after it shown in `timeline`.
+	pub fn find_label(&self, label: Str, timeline: Option<&AssetPath>) -> Option<usize> {
+		let Some(i) = self.lines.iter().position(|line| line.label == Some(label)) else {
+			error!("No dialogue line labelled {label}");
+			return None;
+		};
+		self.next_line(i, timeline)
+	}
+
+	/// What line `i` is remembered as in [`SeenDialogue`].
+	pub fn line_key(&self, i: usize) -> Str {
+		self.lines
+			.get(i)
+			.and_then(|line| line.label)
+			.unwrap_or_else(|| format!("#{i}").as_str().into())
+	}
+}
+
+/// Lines the player has read in any loop, by dialogue path and [`Dialogue::line_key`].
+///
+/// Not reset with the loop, so lines from earlier loops can be skipped.
+#[derive(Resource, Clone, Debug, Default, Deref, DerefMut, Serialize, Deserialize)]
+pub struct SeenDialogue(pub HashSet<(AssetPath<'static>, Str)>);
+
+pub struct DialogueLoader;
+
+impl AssetLoader for DialogueLoader {
+	type Asset = Dialogue;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			Ok(ron::de::from_bytes::<Dialogue>(&bytes)?)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["dlg.ron"]
+	}
+}
+
+/// The dialogue being shown, if any.
+#[derive(Resource, Clone, Debug)]
+pub struct ActiveDialogue {
+	pub path: AssetPath<'static>,
+	pub handle: Handle<Dialogue>,
+	/// Label of the line to start from instead of the first.
+	pub start: Option<Str>,
+	/// The current line, or `None` until the dialogue has loaded.
+	pub line: Option<usize>,
+	/// Characters of the current line revealed so far.
+	pub revealed: f32,
+	/// Whether the current line was already in [`SeenDialogue`] when it was shown.
+	pub seen: bool,
+}
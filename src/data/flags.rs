@@ -0,0 +1,73 @@
+//! Named puzzle and story state that timelines can read and write.
+
+use crate::data::{tl::LoopTime, Str};
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+pub struct FlagDataPlugin;
+
+impl Plugin for FlagDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_type::<WorldFlags>()
+			.register_type::<Flag>()
+			.register_type::<FlagValue>()
+			.init_resource::<WorldFlags>();
+	}
+}
+
+#[derive(Resource, Reflect, Default, Clone, Debug, Deref, DerefMut, Serialize, Deserialize)]
+#[reflect(Resource, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WorldFlags(pub HashMap<Str, Flag>);
+
+impl WorldFlags {
+	pub fn value(&self, flag: &str) -> Option<FlagValue> {
+		self.0.get(&Str::from(flag)).map(|flag| flag.value)
+	}
+
+	/// Forgets everything the player shouldn't remember in the next loop.
+	pub fn reset_loop(&mut self) {
+		self.0.retain(|_, flag| flag.persistent);
+	}
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub struct Flag {
+	pub value: FlagValue,
+	/// Survives [`ResetLoop`](crate::happens::ResetLoop).
+	#[serde(default)]
+	pub persistent: bool,
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum FlagValue {
+	Bool(bool),
+	Int(i64),
+	Time(LoopTime),
+}
+
+impl Default for FlagValue {
+	fn default() -> Self {
+		Self::Bool(true)
+	}
+}
+
+impl FlagValue {
+	/// Anything but `Bool(false)`.
+	pub fn is_truthy(self) -> bool {
+		self != Self::Bool(false)
+	}
+
+	/// Only values of the same kind can be compared.
+	pub fn compare(self, other: Self) -> Option<Ordering> {
+		match (self, other) {
+			(Self::Bool(a), Self::Bool(b)) => Some(a.cmp(&b)),
+			(Self::Int(a), Self::Int(b)) => Some(a.cmp(&b)),
+			(Self::Time(a), Self::Time(b)) => Some(a.cmp(&b)),
+			_ => None,
+		}
+	}
+}
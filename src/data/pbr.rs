@@ -0,0 +1,108 @@
+//! Meshes and materials for scene files.
+
+use super::{replace_paths_with_handles, LoadAsset, LoadStdMat, Str};
+use bevy::{
+	asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, BoxedFuture, LoadContext},
+	prelude::*,
+	scene::SceneLoaderError,
+	utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct PbrDataPlugin;
+
+impl Plugin for PbrDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_asset::<MaterialLibrary>()
+			.register_asset_loader(MaterialLibraryLoader)
+			.register_type::<LoadAsset<StandardMaterial>>()
+			.register_type::<LoadMesh>()
+			.add_systems(
+				Last,
+				(
+					replace_paths_with_handles::<StandardMaterial>,
+					replace_load_meshes,
+				),
+			);
+	}
+}
+
+/// A `.mats.ron` file of named materials. Each is a labeled asset, so scenes can share one with
+/// e.g. `LoadAsset<StandardMaterial>("world.mats.ron#dark_gray")`.
+#[derive(Asset, TypePath, Clone, Debug, Default, Deref, DerefMut)]
+pub struct MaterialLibrary(pub HashMap<Str, Handle<StandardMaterial>>);
+
+pub struct MaterialLibraryLoader;
+
+impl AssetLoader for MaterialLibraryLoader {
+	type Asset = MaterialLibrary;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			let mats = ron::de::from_bytes::<HashMap<Str, LoadStdMat>>(&bytes)?;
+			let mut library = MaterialLibrary::default();
+			for (name, mat) in mats {
+				let mat = mat.load_with(|path| load_context.load(path));
+				let handle = load_context.add_labeled_asset(name.to_string(), mat);
+				library.insert(name, handle);
+			}
+			Ok(library)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["mats.ron"]
+	}
+}
+
+/// A mesh to build or load. Replaced with a `Handle<Mesh>`.
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub enum LoadMesh {
+	Cuboid {
+		x: f32,
+		y: f32,
+		z: f32,
+	},
+	Sphere {
+		radius: f32,
+	},
+	Cylinder {
+		radius: f32,
+		height: f32,
+	},
+	/// Facing up the Z axis, like the ground.
+	Plane {
+		x: f32,
+		y: f32,
+	},
+	/// e.g. `"models/area_1.glb#Mesh0/Primitive0"`.
+	Asset(AssetPath<'static>),
+}
+
+pub fn replace_load_meshes(
+	mut cmds: Commands,
+	q: Query<(Entity, &LoadMesh)>,
+	srv: Res<AssetServer>,
+	mut meshes: ResMut<Assets<Mesh>>,
+) {
+	for (id, to_load) in &q {
+		let handle = match to_load {
+			LoadMesh::Cuboid { x, y, z } => meshes.add(Cuboid::new(*x, *y, *z)),
+			LoadMesh::Sphere { radius } => meshes.add(Sphere::new(*radius)),
+			LoadMesh::Cylinder { radius, height } => meshes.add(Cylinder::new(*radius, *height)),
+			LoadMesh::Plane { x, y } => meshes.add(Plane3d::new(Vec3::Z).mesh().size(*x, *y)),
+			LoadMesh::Asset(path) => srv.load(path.clone()),
+		};
+		cmds.entity(id).insert(handle).remove::<LoadMesh>();
+	}
+}
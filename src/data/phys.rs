@@ -1,20 +1,36 @@
-use bevy::prelude::*;
+use bevy::{
+	asset::{AssetPath, LoadState},
+	ecs::system::SystemParam,
+	prelude::*,
+	render::mesh::{PrimitiveTopology, VertexAttributeValues},
+};
 use bevy_xpbd_3d::{
-	parry::{na::Unit, shape::SharedShape},
-	prelude::Collider,
+	parry::{
+		math::{Isometry, Point, Real},
+		na::{DMatrix, Unit},
+		shape::SharedShape,
+	},
+	prelude::{Collider, LockedAxes, Restitution, RigidBody},
 };
 use serde::{Deserialize, Serialize};
+use std::{
+	error::Error,
+	fmt::{self, Display, Formatter},
+};
 
 pub struct PhysDataPlugin;
 
 impl Plugin for PhysDataPlugin {
 	fn build(&self, app: &mut App) {
 		app.register_type::<ColliderShape>()
-			.add_systems(First, insert_collider_shapes)
-			.add_systems(PreUpdate, insert_collider_shapes)
-			.add_systems(Update, insert_collider_shapes)
-			.add_systems(PostUpdate, insert_collider_shapes)
-			.add_systems(Last, insert_collider_shapes);
+			.register_type::<MeshShape>()
+			.register_type::<LoadBody>()
+			.register_type::<BodyKind>()
+			.add_systems(First, (insert_collider_shapes, insert_bodies))
+			.add_systems(PreUpdate, (insert_collider_shapes, insert_bodies))
+			.add_systems(Update, (insert_collider_shapes, insert_bodies))
+			.add_systems(PostUpdate, (insert_collider_shapes, insert_bodies))
+			.add_systems(Last, (insert_collider_shapes, insert_bodies));
 	}
 }
 
@@ -43,14 +59,32 @@ pub enum ColliderShape {
 		b: Vec3,
 		c: Vec3,
 	},
-	TriMesh {},
-	Polyline {},
+	TriMesh {
+		vertices: Vec<Vec3>,
+		indices: Vec<[u32; 3]>,
+	},
+	Polyline {
+		vertices: Vec<Vec3>,
+		/// Segments between `vertices`. If `None`, each vertex connects to the next.
+		#[serde(default)]
+		indices: Option<Vec<[u32; 2]>>,
+	},
 	HalfSpace {
 		normal: Vec3,
 	},
-	HeightField {},
-	Compound {},
-	ConvexPolyhedron {},
+	/// `heights[row][column]`, spread evenly over `scale.x` by `scale.z`.
+	HeightField {
+		heights: Vec<Vec<f32>>,
+		scale: Vec3,
+	},
+	/// The `scale` of each child's transform is ignored.
+	Compound {
+		shapes: Vec<(Transform, ColliderShape)>,
+	},
+	/// Convex hull of `points`.
+	ConvexPolyhedron {
+		points: Vec<Vec3>,
+	},
 	Cylinder {
 		half_height: f32,
 		radius: f32,
@@ -81,8 +115,29 @@ pub enum ColliderShape {
 		radius: f32,
 		border_radius: f32,
 	},
-	RoundConvexPolyhedron {},
-	Custom {},
+	RoundConvexPolyhedron {
+		points: Vec<Vec3>,
+		border_radius: f32,
+	},
+	/// Derived from a [`Mesh`] asset, e.g. `"models/area_1.glb#Mesh0/Primitive0"`.
+	Custom {
+		mesh: AssetPath<'static>,
+		#[serde(default)]
+		kind: MeshShape,
+	},
+}
+
+/// How [`ColliderShape::Custom`] turns a mesh into a collider.
+#[derive(Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[reflect(Default, Serialize, Deserialize)]
+pub enum MeshShape {
+	/// Exact triangles of the mesh. Best for static level geometry.
+	#[default]
+	TriMesh,
+	/// Convex hull of the mesh's vertices.
+	ConvexHull,
+	/// Approximates the mesh with several convex parts. Slow to build.
+	ConvexDecomposition,
 }
 
 impl Default for ColliderShape {
@@ -91,67 +146,307 @@ impl Default for ColliderShape {
 	}
 }
 
-pub fn insert_collider_shapes(mut cmds: Commands, q: Query<(Entity, &ColliderShape)>) {
-	for (id, shape) in &q {
-		cmds.entity(id)
-			.insert(Collider::from(shape.clone()))
-			.remove::<ColliderShape>();
+/// A [`RigidBody`] and the physics components that go with it. Replaced with the real ones.
+#[derive(Reflect, Component, Clone, Debug, Default, Serialize, Deserialize)]
+#[reflect(Default, Component, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadBody {
+	pub kind: BodyKind,
+	/// Axes it can't move along.
+	pub lock_translation: BVec3,
+	/// Axes it can't rotate around.
+	pub lock_rotation: BVec3,
+	pub restitution: Option<f32>,
+}
+
+#[derive(Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[reflect(Default, Serialize, Deserialize)]
+pub enum BodyKind {
+	Dynamic,
+	#[default]
+	Static,
+	Kinematic,
+}
+
+impl From<BodyKind> for RigidBody {
+	fn from(value: BodyKind) -> Self {
+		match value {
+			BodyKind::Dynamic => RigidBody::Dynamic,
+			BodyKind::Static => RigidBody::Static,
+			BodyKind::Kinematic => RigidBody::Kinematic,
+		}
+	}
+}
+
+impl LoadBody {
+	pub fn locked_axes(&self) -> LockedAxes {
+		let mut axes = LockedAxes::new();
+		let BVec3 { x, y, z } = self.lock_translation;
+		if x {
+			axes = axes.lock_translation_x();
+		}
+		if y {
+			axes = axes.lock_translation_y();
+		}
+		if z {
+			axes = axes.lock_translation_z();
+		}
+		let BVec3 { x, y, z } = self.lock_rotation;
+		if x {
+			axes = axes.lock_rotation_x();
+		}
+		if y {
+			axes = axes.lock_rotation_y();
+		}
+		if z {
+			axes = axes.lock_rotation_z();
+		}
+		axes
+	}
+}
+
+pub fn insert_bodies(mut cmds: Commands, q: Query<(Entity, &LoadBody)>) {
+	for (id, body) in &q {
+		let mut cmds = cmds.entity(id);
+		cmds.insert((RigidBody::from(body.kind), body.locked_axes()))
+			.remove::<LoadBody>();
+		if let Some(restitution) = body.restitution {
+			cmds.insert(Restitution::new(restitution));
+		}
 	}
 }
 
-impl From<ColliderShape> for SharedShape {
-	fn from(value: ColliderShape) -> Self {
+/// Keeps meshes needed by a [`ColliderShape::Custom`] alive until they finish loading.
+#[derive(Component, Default, Debug)]
+pub struct PendingColliderMeshes(pub Vec<Handle<Mesh>>);
+
+pub fn insert_collider_shapes(
+	mut cmds: Commands,
+	mut q: Query<(Entity, &ColliderShape, Option<&mut PendingColliderMeshes>)>,
+	meshes: ColliderMeshes,
+) {
+	for (id, shape, pending) in &mut q {
+		match shape.to_shared_shape(Some(&meshes)) {
+			Ok(shape) => {
+				cmds.entity(id)
+					.insert(Collider::from(shape))
+					.remove::<(ColliderShape, PendingColliderMeshes)>();
+			}
+			Err(ShapeError::MeshNotLoaded(handle)) => match pending {
+				Some(mut pending) => {
+					if !pending.0.contains(&handle) {
+						pending.0.push(handle);
+					}
+				}
+				None => {
+					cmds.entity(id).insert(PendingColliderMeshes(vec![handle]));
+				}
+			},
+			Err(e) => {
+				error!("Invalid collider shape on {id:?}: {e}");
+				cmds.entity(id)
+					.remove::<(ColliderShape, PendingColliderMeshes)>();
+			}
+		}
+	}
+}
+
+/// Looks up the meshes referenced by [`ColliderShape::Custom`].
+#[derive(SystemParam)]
+pub struct ColliderMeshes<'w> {
+	pub asset_server: Res<'w, AssetServer>,
+	pub meshes: Res<'w, Assets<Mesh>>,
+}
+
+impl<'w> ColliderMeshes<'w> {
+	pub fn get(&self, path: &AssetPath<'static>) -> Result<&Mesh, ShapeError> {
+		let handle = self.asset_server.load::<Mesh>(path.clone());
+		if let Some(mesh) = self.meshes.get(&handle) {
+			return Ok(mesh);
+		}
+		match self.asset_server.load_state(&handle) {
+			LoadState::Failed => Err(ShapeError::MeshFailed(path.clone())),
+			_ => Err(ShapeError::MeshNotLoaded(handle)),
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum ShapeError {
+	/// The mesh is still loading. Try again later.
+	MeshNotLoaded(Handle<Mesh>),
+	MeshFailed(AssetPath<'static>),
+	/// The mesh isn't a triangle list with `Float32x3` positions.
+	UnsupportedMesh(AssetPath<'static>),
+	/// Mesh-derived shapes were converted without access to `Assets<Mesh>`.
+	NoMeshes(AssetPath<'static>),
+	/// The points are all coplanar, or there aren't enough of them.
+	DegenerateHull,
+	/// Rows of a height field must all be the same length.
+	RaggedHeights,
+}
+
+impl Display for ShapeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ShapeError::MeshNotLoaded(handle) => {
+				write!(f, "mesh {:?} is still loading", handle.id())
+			}
+			ShapeError::MeshFailed(path) => write!(f, "mesh {path} failed to load"),
+			ShapeError::UnsupportedMesh(path) => write!(
+				f,
+				"mesh {path} must be a triangle list with Float32x3 positions"
+			),
+			ShapeError::NoMeshes(path) => write!(f, "can't use mesh {path} here"),
+			ShapeError::DegenerateHull => {
+				f.write_str("can't build a convex hull from these points")
+			}
+			ShapeError::RaggedHeights => f.write_str("height field rows have different lengths"),
+		}
+	}
+}
+
+impl Error for ShapeError {}
+
+impl ColliderShape {
+	/// Builds the shape. `meshes` is only needed for [`ColliderShape::Custom`].
+	pub fn to_shared_shape(
+		&self,
+		meshes: Option<&ColliderMeshes>,
+	) -> Result<SharedShape, ShapeError> {
 		use ColliderShape::*;
-		match value {
-			Ball { radius } => SharedShape::ball(radius),
+		let shape = match self {
+			Ball { radius } => SharedShape::ball(*radius),
 			Cuboid { x, y, z } => SharedShape::cuboid(x * 0.5, y * 0.5, z * 0.5),
-			Capsule { a, b, radius } => SharedShape::capsule(a.into(), b.into(), radius),
-			Segment { a, b } => SharedShape::segment(a.into(), b.into()),
-			Triangle { a, b, c } => SharedShape::triangle(a.into(), b.into(), c.into()),
-			TriMesh {} => todo!(),
-			Polyline {} => todo!(),
-			HalfSpace { normal } => SharedShape::halfspace(Unit::new_normalize(normal.into())),
-			HeightField {} => todo!(),
-			Compound {} => todo!(),
-			ConvexPolyhedron {} => todo!(),
+			Capsule { a, b, radius } => SharedShape::capsule((*a).into(), (*b).into(), *radius),
+			Segment { a, b } => SharedShape::segment((*a).into(), (*b).into()),
+			Triangle { a, b, c } => SharedShape::triangle((*a).into(), (*b).into(), (*c).into()),
+			TriMesh { vertices, indices } => {
+				SharedShape::trimesh(points(vertices), indices.clone())
+			}
+			Polyline { vertices, indices } => {
+				SharedShape::polyline(points(vertices), indices.clone())
+			}
+			HalfSpace { normal } => SharedShape::halfspace(Unit::new_normalize((*normal).into())),
+			HeightField { heights, scale } => {
+				let cols = heights.first().map_or(0, Vec::len);
+				if heights.iter().any(|row| row.len() != cols) {
+					return Err(ShapeError::RaggedHeights);
+				}
+				let heights = DMatrix::from_fn(heights.len(), cols, |r, c| heights[r][c]);
+				SharedShape::heightfield(heights, (*scale).into())
+			}
+			Compound { shapes } => SharedShape::compound(
+				shapes
+					.iter()
+					.map(|(xform, shape)| Ok((isometry(xform), shape.to_shared_shape(meshes)?)))
+					.collect::<Result<_, ShapeError>>()?,
+			),
+			ConvexPolyhedron { points: pts } => {
+				SharedShape::convex_hull(&points(pts)).ok_or(ShapeError::DegenerateHull)?
+			}
 			Cylinder {
 				half_height,
 				radius,
-			} => SharedShape::cylinder(half_height, radius),
+			} => SharedShape::cylinder(*half_height, *radius),
 			Cone {
 				half_height,
 				radius,
-			} => SharedShape::cone(half_height, radius),
+			} => SharedShape::cone(*half_height, *radius),
 			RoundCuboid {
 				x,
 				y,
 				z,
 				border_radius,
-			} => SharedShape::round_cuboid(x * 0.5, y * 0.5, z * 0.5, border_radius),
+			} => SharedShape::round_cuboid(x * 0.5, y * 0.5, z * 0.5, *border_radius),
 			RoundTriangle {
 				a,
 				b,
 				c,
 				border_radius,
-			} => SharedShape::round_triangle(a.into(), b.into(), c.into(), border_radius),
+			} => SharedShape::round_triangle((*a).into(), (*b).into(), (*c).into(), *border_radius),
 			RoundCylinder {
 				half_height,
 				radius,
 				border_radius,
-			} => SharedShape::round_cylinder(half_height, radius, border_radius),
+			} => SharedShape::round_cylinder(*half_height, *radius, *border_radius),
 			RoundCone {
 				half_height,
 				radius,
 				border_radius,
-			} => SharedShape::round_cone(half_height, radius, border_radius),
-			RoundConvexPolyhedron {} => todo!(),
-			Custom {} => todo!(),
-		}
+			} => SharedShape::round_cone(*half_height, *radius, *border_radius),
+			RoundConvexPolyhedron {
+				points: pts,
+				border_radius,
+			} => SharedShape::round_convex_hull(&points(pts), *border_radius)
+				.ok_or(ShapeError::DegenerateHull)?,
+			Custom { mesh, kind } => {
+				let Some(meshes) = meshes else {
+					return Err(ShapeError::NoMeshes(mesh.clone()));
+				};
+				let (vertices, indices) = mesh_data(meshes.get(mesh)?)
+					.ok_or_else(|| ShapeError::UnsupportedMesh(mesh.clone()))?;
+				match kind {
+					MeshShape::TriMesh => SharedShape::trimesh(vertices, indices),
+					MeshShape::ConvexHull => {
+						SharedShape::convex_hull(&vertices).ok_or(ShapeError::DegenerateHull)?
+					}
+					MeshShape::ConvexDecomposition => {
+						SharedShape::convex_decomposition(&vertices, &indices)
+					}
+				}
+			}
+		};
+		Ok(shape)
+	}
+}
+
+/// The translation and rotation of `xform`. Parry shapes can't be scaled this way.
+pub fn isometry(xform: &Transform) -> Isometry<Real> {
+	Isometry::new(
+		xform.translation.into(),
+		xform.rotation.to_scaled_axis().into(),
+	)
+}
+
+fn points(vertices: &[Vec3]) -> Vec<Point<Real>> {
+	vertices.iter().map(|v| (*v).into()).collect()
+}
+
+/// Vertices and triangles of a triangle list mesh.
+pub fn mesh_data(mesh: &Mesh) -> Option<(Vec<Point<Real>>, Vec<[u32; 3]>)> {
+	if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+		return None;
+	}
+	let Some(VertexAttributeValues::Float32x3(positions)) =
+		mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+	else {
+		return None;
+	};
+	let vertices = positions.iter().map(|p| Point::from(*p)).collect();
+	let indices = match mesh.indices() {
+		Some(indices) => indices.iter().map(|i| i as u32).collect::<Vec<_>>(),
+		None => (0..positions.len() as u32).collect(),
+	};
+	let indices = indices
+		.chunks_exact(3)
+		.map(|tri| [tri[0], tri[1], tri[2]])
+		.collect();
+	Some((vertices, indices))
+}
+
+impl TryFrom<ColliderShape> for SharedShape {
+	type Error = ShapeError;
+
+	fn try_from(value: ColliderShape) -> Result<Self, Self::Error> {
+		value.to_shared_shape(None)
 	}
 }
 
-impl From<ColliderShape> for Collider {
-	fn from(value: ColliderShape) -> Self {
-		Self::from(SharedShape::from(value))
+impl TryFrom<ColliderShape> for Collider {
+	type Error = ShapeError;
+
+	fn try_from(value: ColliderShape) -> Result<Self, Self::Error> {
+		SharedShape::try_from(value).map(Self::from)
 	}
 }
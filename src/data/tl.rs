@@ -1,20 +1,27 @@
 use bevy::{
-	asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, BoxedFuture, LoadContext},
+	asset::{
+		io::{Reader, Writer},
+		saver::{AssetSaver, SavedAsset},
+		AssetLoader, AssetPath, AsyncReadExt, AsyncWriteExt, BoxedFuture, LoadContext,
+	},
 	ecs::system::Command,
 	prelude::*,
 	reflect::{
-		serde::TypedReflectDeserializer, List, ListIter, ReflectMut, ReflectOwned, ReflectRef,
-		TypeInfo, TypeRegistry, TypeRegistryArc,
+		serde::{TypedReflectDeserializer, TypedReflectSerializer},
+		List, ListIter, ReflectMut, ReflectOwned, ReflectRef, TypeInfo, TypeRegistry,
+		TypeRegistryArc,
 	},
 	scene::SceneLoaderError,
 };
 use humantime::DurationError;
+use ron::{extensions::Extensions, ser::PrettyConfig};
 use serde::{
 	de::{DeserializeSeed, Error, MapAccess, Visitor},
+	ser::{self, SerializeMap, SerializeSeq, SerializeStruct},
 	Deserialize, Deserializer, Serialize, Serializer,
 };
 use std::{
-	any::Any,
+	any::{Any, TypeId},
 	borrow::Cow,
 	collections::BTreeMap,
 	fmt::{Debug, Display, Formatter},
@@ -27,21 +34,25 @@ use bevy::utils::{CowArc, HashMap};
 use bevy_asset_loader::prelude::*;
 
 use super::Str;
+use crate::GameState;
 use serde::de::SeqAccess;
 
 pub struct TimeDataPlugin;
 
+/// Path of the [`TimelineIndex`] listing every timeline in the game.
+pub const TIMELINE_INDEX: &str = "tl/timelines.index.ron";
+
 #[derive(AssetCollection, Resource)]
 pub struct Timelines {
-	#[asset(path = "tl/intro.tl.ron")]
-	pub intro: Handle<Timeline>,
-	#[asset(path = "tl/area_1.tl.ron")]
-	pub area_1: Handle<Timeline>,
+	#[asset(path = "tl/timelines.index.ron")]
+	pub index: Handle<TimelineIndex>,
 }
 
 impl Plugin for TimeDataPlugin {
 	fn build(&self, app: &mut App) {
 		app.init_asset::<Timeline>()
+			.init_asset::<TimelineIndex>()
+			.register_asset_loader(TimelineIndexLoader)
 			.register_type::<Log>()
 			.register_type::<LoopTime>()
 			.register_type::<T>()
@@ -49,10 +60,20 @@ impl Plugin for TimeDataPlugin {
 			.register_type::<Str>()
 			.register_type::<MomentRef>()
 			.register_type::<TimeLoop>()
+			.register_type::<TimeScale>()
+			.register_type::<TimeDilationZone>()
+			.init_resource::<TimeScale>()
+			.init_resource::<LoopDelta>()
 			.register_type::<(AssetPath<'static>, T)>()
 			.register_type::<PortalPath>()
+			.register_type::<Portal>()
 			.register_type::<PortalTo>()
-			.register_type::<Trigger>();
+			.register_type::<LoadPortal>()
+			.register_type::<Trigger>()
+			.register_type::<TriggerKind>()
+			.register_type::<Activator>()
+			.add_systems(OnExit(GameState::Loading), init_time_loop)
+			.add_systems(PreUpdate, sync_loaded_timelines);
 	}
 
 	fn finish(&self, app: &mut App) {
@@ -63,20 +84,108 @@ impl Plugin for TimeDataPlugin {
 			asset_server: asset_server.clone(),
 		});
 
-		let assets = app.world.resource::<AssetServer>();
-
-		let intro_path = AssetPath::from("tl/intro.tl.ron");
-		let intro = assets.load(intro_path.clone());
-		let area_path = AssetPath::from("tl/area_1.tl.ron");
-		let area_1 = assets.load(area_path.clone());
+		let index = app
+			.world
+			.resource::<AssetServer>()
+			.load::<TimelineIndex>(TIMELINE_INDEX);
+		app.insert_resource(Timelines { index });
 		app.insert_resource(TimeLoop {
-			curr: T(intro.id(), default()),
+			// Replaced by the index's `start` once it loads.
+			curr: T(AssetId::invalid(), default()),
 			resetting_from: default(),
 			resetting_to: default(),
 		});
-		app.insert_resource(LoadedTimelines(
-			[(area_path, area_1), (intro_path, intro)].into(),
-		));
+		app.insert_resource(LoadedTimelines(default()));
+	}
+}
+
+/// Manifest of every timeline in the game and where the loop starts.
+///
+/// Loading it loads all of the listed timelines as dependencies, so adding an area only needs a
+/// new entry here.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineIndex {
+	pub start: TPath,
+	pub timelines: HashMap<AssetPath<'static>, TimelineMeta>,
+	#[serde(skip)]
+	pub handles: HashMap<AssetPath<'static>, Handle<Timeline>>,
+}
+
+/// Optional details about a timeline listed in a [`TimelineIndex`].
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimelineMeta {
+	pub name: Option<String>,
+	pub desc: Option<String>,
+}
+
+pub struct TimelineIndexLoader;
+
+impl AssetLoader for TimelineIndexLoader {
+	type Asset = TimelineIndex;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			let mut index = ron::de::from_bytes::<TimelineIndex>(&bytes)?;
+			index.handles = index
+				.timelines
+				.keys()
+				.map(|path| (path.clone(), load_context.load(path.clone())))
+				.collect();
+			Ok(index)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["index.ron"]
+	}
+}
+
+/// Fills [`LoadedTimelines`] from the [`TimelineIndex`], and starts the loop at its `start`
+/// unless it is already somewhere (e.g. a test jumped elsewhere).
+pub fn init_time_loop(
+	timelines: Res<Timelines>,
+	indices: Res<Assets<TimelineIndex>>,
+	assets: Res<Assets<Timeline>>,
+	srv: Res<AssetServer>,
+	mut loaded: ResMut<LoadedTimelines>,
+	mut tloop: ResMut<TimeLoop>,
+) {
+	let Some(index) = indices.get(&timelines.index) else {
+		error!("Timeline index {TIMELINE_INDEX} is not loaded");
+		return;
+	};
+	loaded.extend(index.handles.clone());
+	if assets.contains(tloop.curr.0) {
+		return;
+	}
+	match srv.t_for_t_path(index.start.clone()) {
+		Some(start) => tloop.curr = start,
+		None => error!("Start {:?} is not listed in {TIMELINE_INDEX}", index.start),
+	}
+}
+
+/// Keeps [`LoadedTimelines`] up to date when timelines are added to the index while running.
+pub fn sync_loaded_timelines(
+	mut events: EventReader<AssetEvent<TimelineIndex>>,
+	indices: Res<Assets<TimelineIndex>>,
+	mut loaded: ResMut<LoadedTimelines>,
+) {
+	for ev in events.read() {
+		if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = ev {
+			if let Some(index) = indices.get(*id) {
+				loaded.extend(index.handles.clone());
+			}
+		}
 	}
 }
 
@@ -118,10 +227,10 @@ impl FromStr for LoopTime {
 					.map_err(|_| DurationError::NumberOverflow)
 			})
 		}
-		match s.as_bytes()[0] {
-			b'-' => parse_unsigned(&s[1..]).map(std::ops::Neg::neg),
-			b'+' => parse_unsigned(&s[1..]),
-			_ => parse_unsigned(s),
+		if let Some(s) = s.strip_prefix('-') {
+			parse_unsigned(s).map(std::ops::Neg::neg)
+		} else {
+			parse_unsigned(s.strip_prefix('+').unwrap_or(s))
 		}
 		.map(Self)
 	}
@@ -217,6 +326,10 @@ impl SubAssign for LoopTime {
 impl LoopTime {
 	pub const EPOCH: Self = Self(0);
 
+	pub const fn from_secs(secs: i64) -> Self {
+		Self(secs * 1000)
+	}
+
 	pub fn millis(self) -> i64 {
 		self.0
 	}
@@ -236,6 +349,24 @@ pub struct Timeline {
 	#[deref]
 	pub moments: BTreeMap<LoopTime, Moment>,
 	pub merge_into: Option<T>,
+	/// Links whose timeline had not been requested from the `AssetServer` when this one loaded.
+	pub unresolved: Vec<(TimelineLink, TPath)>,
+}
+
+/// Which end of a [`Timeline`] connects it to another one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TimelineLink {
+	BranchFrom,
+	MergeInto,
+}
+
+impl Display for TimelineLink {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			TimelineLink::BranchFrom => f.write_str("branch_from"),
+			TimelineLink::MergeInto => f.write_str("merge_into"),
+		}
+	}
 }
 
 impl Timeline {
@@ -268,10 +399,30 @@ pub struct Moment {
 	pub disabled: bool,
 }
 
+#[derive(Default)]
 pub struct Happenings {
 	pub label: Option<Str>,
 	pub actions: Vec<Box<dyn Do>>,
 	pub disabled: bool,
+	/// Only happens if all of these hold when the moment is reached.
+	pub when: Vec<Box<dyn Condition>>,
+	/// Doesn't happen if any of these hold when the moment is reached.
+	pub unless: Vec<Box<dyn Condition>>,
+}
+
+impl Happenings {
+	pub fn is_conditional(&self) -> bool {
+		!self.when.is_empty() || !self.unless.is_empty()
+	}
+
+	/// Checks `when` and `unless` against the current state of the world.
+	pub fn conditions_hold(
+		when: &[Box<dyn Condition>],
+		unless: &[Box<dyn Condition>],
+		world: &mut World,
+	) -> bool {
+		when.iter().all(|cond| cond.check(world)) && !unless.iter().any(|cond| cond.check(world))
+	}
 }
 
 #[derive(Reflect, Copy, Clone, Debug, Default)]
@@ -319,6 +470,12 @@ impl IndexMut<T> for Assets<Timeline> {
 #[reflect_trait]
 pub trait Do: Reflect + Send + Sync {
 	fn apply(&self, cmds: Commands);
+	/// Queues the inverse of [`Do::apply`], if the type has registered a [`ReflectUndo`].
+	///
+	/// Returns `false` if it can't be undone, in which case only
+	/// [`reset_world`](crate::happens::reset_world) will put things back.
+	fn undo(&self, cmds: Commands) -> bool;
+	fn reversible(&self) -> bool;
 	fn clone_do(&self) -> Box<dyn Do>;
 }
 
@@ -327,11 +484,50 @@ impl<T: Command + Clone + Reflect + Send + Sync> Do for T {
 		cmds.add(self.to_owned())
 	}
 
+	fn undo(&self, mut cmds: Commands) -> bool {
+		let Some(undo) = crate::type_registry()
+			.read()
+			.get_type_data::<ReflectUndo>(TypeId::of::<T>())
+			.cloned()
+		else {
+			return false;
+		};
+		let this = self.clone();
+		cmds.add(move |world: &mut World| {
+			if let Some(reverse) = undo.get(this.as_reflect()) {
+				reverse.undo(world)
+			}
+		});
+		true
+	}
+
+	fn reversible(&self) -> bool {
+		crate::type_registry()
+			.read()
+			.get_type_data::<ReflectUndo>(TypeId::of::<T>())
+			.is_some()
+	}
+
 	fn clone_do(&self) -> Box<dyn Do> {
 		Box::new(self.clone())
 	}
 }
 
+/// Implemented by happenings that can be played backwards while the loop rewinds.
+///
+/// Register with `#[reflect(Undo)]` so [`Do::undo`] can find it.
+#[reflect_trait]
+pub trait Undo {
+	fn undo(&self, world: &mut World);
+}
+
+/// A predicate on world state that gates a [`Happenings`] group.
+#[reflect_trait]
+pub trait Condition: Reflect + Send + Sync {
+	fn check(&self, world: &mut World) -> bool;
+	fn clone_condition(&self) -> Box<dyn Condition>;
+}
+
 /// A dummy happening for debugging time graph code
 #[derive(Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Do, Serialize, Deserialize)]
@@ -410,8 +606,8 @@ pub enum TimelineField {
 }
 
 pub struct TimelineDeserializer<'a> {
-	registry: &'a TypeRegistry,
-	asset_server: &'a AssetServer,
+	pub registry: &'a TypeRegistry,
+	pub asset_server: &'a AssetServer,
 }
 
 impl<'de> DeserializeSeed<'de> for TimelineDeserializer<'de> {
@@ -451,10 +647,15 @@ impl<'a, 'de> Visitor<'de> for TimelineVisitor<'a> {
 		let mut branch_from = None;
 		let mut merge_into = None;
 		let mut moments = None;
+		let mut unresolved = Vec::new();
 		while let Some(key) = map.next_key()? {
 			match key {
 				TimelineField::BranchFrom => {
-					branch_from = self.asset_server.t_for_t_path(map.next_value()?)
+					let path = map.next_value::<TPath>()?;
+					branch_from = self.asset_server.t_for_t_path(path.clone());
+					if branch_from.is_none() {
+						unresolved.push((TimelineLink::BranchFrom, path));
+					}
 				}
 				TimelineField::Moments => {
 					moments = Some(map.next_value_seed(MomentMapDeserializer {
@@ -462,7 +663,11 @@ impl<'a, 'de> Visitor<'de> for TimelineVisitor<'a> {
 					})?)
 				}
 				TimelineField::MergeInto => {
-					merge_into = self.asset_server.t_for_t_path(map.next_value()?)
+					let path = map.next_value::<TPath>()?;
+					merge_into = self.asset_server.t_for_t_path(path.clone());
+					if merge_into.is_none() {
+						unresolved.push((TimelineLink::MergeInto, path));
+					}
 				}
 			}
 		}
@@ -472,6 +677,7 @@ impl<'a, 'de> Visitor<'de> for TimelineVisitor<'a> {
 			branch_from,
 			moments,
 			merge_into,
+			unresolved,
 		})
 	}
 }
@@ -671,6 +877,8 @@ impl<'a, 'de> Visitor<'de> for HappeningsMapVisitor<'a> {
 		let mut actions = Vec::new();
 		let mut disabled = false;
 		let mut label = None;
+		let mut when = Vec::new();
+		let mut unless = Vec::new();
 
 		while let Some(key) = map.next_key()? {
 			if key == "DISABLED" {
@@ -683,6 +891,20 @@ impl<'a, 'de> Visitor<'de> for HappeningsMapVisitor<'a> {
 				continue;
 			}
 
+			if key == "IF" {
+				when = map.next_value_seed(ConditionsDeserializer {
+					registry: self.registry,
+				})?;
+				continue;
+			}
+
+			if key == "UNLESS" {
+				unless = map.next_value_seed(ConditionsDeserializer {
+					registry: self.registry,
+				})?;
+				continue;
+			}
+
 			let reg = self
 				.registry
 				.get_with_type_path(key)
@@ -701,10 +923,323 @@ impl<'a, 'de> Visitor<'de> for HappeningsMapVisitor<'a> {
 			label,
 			actions,
 			disabled,
+			when,
+			unless,
+		})
+	}
+}
+
+/// A map of `TypePath`s => [`Condition`] implementors.
+pub struct ConditionsDeserializer<'a> {
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ConditionsDeserializer<'a> {
+	type Value = Vec<Box<dyn Condition>>;
+
+	fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_map(ConditionsVisitor {
+			registry: self.registry,
+		})
+	}
+}
+
+pub struct ConditionsVisitor<'a> {
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> Visitor<'de> for ConditionsVisitor<'a> {
+	type Value = Vec<Box<dyn Condition>>;
+
+	fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+		formatter.write_str("a map of `TypePath`s => `Condition` implementors")
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+	where
+		A: MapAccess<'de>,
+	{
+		let mut conditions = Vec::new();
+		while let Some(key) = map.next_key()? {
+			let reg = self
+				.registry
+				.get_with_type_path(key)
+				.ok_or_else(|| Error::custom(format_args!("No registration found for `{key}`")))?;
+
+			let entry = map.next_value_seed(TypedReflectDeserializer::new(reg, self.registry))?;
+
+			let condition = condition_from_reflect(entry, self.registry).map_err(|e| {
+				Error::custom(format_args!(
+					"Failed to downcast {e:?} to `Box<dyn Condition>`"
+				))
+			})?;
+
+			conditions.push(condition);
+		}
+		Ok(conditions)
+	}
+}
+
+/// Writes a [`Timeline`] in the same format [`TimelineLoader`] reads.
+///
+/// Not registered with the app since we don't use the asset processor, but used
+/// by tools that edit timelines.
+pub struct TimelineSaver {
+	pub registry: TypeRegistryArc,
+	pub asset_server: AssetServer,
+}
+
+impl AssetSaver for TimelineSaver {
+	type Asset = Timeline;
+	type Settings = ();
+	type OutputLoader = TimelineLoader;
+	type Error = ron::Error;
+
+	fn save<'a>(
+		&'a self,
+		writer: &'a mut Writer,
+		asset: SavedAsset<'a, Self::Asset>,
+		_settings: &'a Self::Settings,
+	) -> BoxedFuture<'a, Result<(), Self::Error>> {
+		Box::pin(async move {
+			let ron = TimelineSerializer {
+				timeline: asset.get(),
+				registry: &self.registry.read(),
+				asset_server: &self.asset_server,
+			}
+			.to_ron_string()?;
+			writer.write_all(ron.as_bytes()).await?;
+			Ok(())
 		})
 	}
 }
 
+pub struct TimelineSerializer<'a> {
+	pub timeline: &'a Timeline,
+	pub registry: &'a TypeRegistry,
+	pub asset_server: &'a AssetServer,
+}
+
+impl<'a> TimelineSerializer<'a> {
+	pub fn to_ron_string(&self) -> ron::Result<String> {
+		ron::ser::to_string_pretty(
+			self,
+			PrettyConfig::new()
+				.indentor("\t".to_owned())
+				.extensions(Extensions::IMPLICIT_SOME),
+		)
+	}
+}
+
+impl<'a> Serialize for TimelineSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let t_path = |link: TimelineLink, t: T| {
+			self.asset_server.t_path_for_t(t).ok_or_else(|| {
+				<S::Error as ser::Error>::custom(format_args!(
+					"no path for `{link}` timeline {}",
+					t.0
+				))
+			})
+		};
+		let branch_from = self
+			.timeline
+			.branch_from
+			.map(|t| t_path(TimelineLink::BranchFrom, t))
+			.transpose()?;
+		let merge_into = self
+			.timeline
+			.merge_into
+			.map(|t| t_path(TimelineLink::MergeInto, t))
+			.transpose()?;
+
+		let len = 1 + branch_from.is_some() as usize + merge_into.is_some() as usize;
+		let mut state = serializer.serialize_struct("Timeline", len)?;
+		if let Some(branch_from) = branch_from {
+			state.serialize_field("branch_from", &branch_from)?;
+		}
+		state.serialize_field(
+			"moments",
+			&MomentMapSerializer {
+				moments: &self.timeline.moments,
+				registry: self.registry,
+			},
+		)?;
+		if let Some(merge_into) = merge_into {
+			state.serialize_field("merge_into", &merge_into)?;
+		}
+		state.end()
+	}
+}
+
+pub struct MomentMapSerializer<'a> {
+	pub moments: &'a BTreeMap<LoopTime, Moment>,
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for MomentMapSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let mut state = serializer.serialize_map(Some(self.moments.len()))?;
+		for (t, moment) in self.moments {
+			state.serialize_entry(
+				t,
+				&MomentSerializer {
+					moment,
+					registry: self.registry,
+				},
+			)?;
+		}
+		state.end()
+	}
+}
+
+pub struct MomentSerializer<'a> {
+	pub moment: &'a Moment,
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for MomentSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let Moment {
+			label,
+			desc,
+			happenings,
+			disabled,
+		} = self.moment;
+		let len = 1 + label.is_some() as usize + desc.is_some() as usize + *disabled as usize;
+		let mut state = serializer.serialize_struct("Moment", len)?;
+		if let Some(label) = label {
+			state.serialize_field("label", label)?;
+		}
+		if let Some(desc) = desc {
+			state.serialize_field("desc", &**desc)?;
+		}
+		state.serialize_field(
+			"happenings",
+			&HappeningsSerializer {
+				happenings,
+				registry: self.registry,
+			},
+		)?;
+		if *disabled {
+			state.serialize_field("disabled", disabled)?;
+		}
+		state.end()
+	}
+}
+
+pub struct HappeningsSerializer<'a> {
+	pub happenings: &'a [Happenings],
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for HappeningsSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let mut state = serializer.serialize_seq(Some(self.happenings.len()))?;
+		for happenings in self.happenings {
+			state.serialize_element(&HappeningsMapSerializer {
+				happenings,
+				registry: self.registry,
+			})?;
+		}
+		state.end()
+	}
+}
+
+pub struct HappeningsMapSerializer<'a> {
+	pub happenings: &'a Happenings,
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for HappeningsMapSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let Happenings {
+			label,
+			actions,
+			disabled,
+			when,
+			unless,
+		} = self.happenings;
+		let len = actions.len()
+			+ label.is_some() as usize
+			+ *disabled as usize
+			+ !when.is_empty() as usize
+			+ !unless.is_empty() as usize;
+		let mut state = serializer.serialize_map(Some(len))?;
+		if let Some(label) = label {
+			state.serialize_entry("LABEL", label)?;
+		}
+		if *disabled {
+			state.serialize_entry("DISABLED", disabled)?;
+		}
+		if !when.is_empty() {
+			state.serialize_entry(
+				"IF",
+				&ConditionsSerializer {
+					conditions: when,
+					registry: self.registry,
+				},
+			)?;
+		}
+		if !unless.is_empty() {
+			state.serialize_entry(
+				"UNLESS",
+				&ConditionsSerializer {
+					conditions: unless,
+					registry: self.registry,
+				},
+			)?;
+		}
+		for action in actions {
+			let action = action.as_reflect();
+			state.serialize_entry(
+				action.reflect_type_path(),
+				&TypedReflectSerializer::new(action, self.registry),
+			)?;
+		}
+		state.end()
+	}
+}
+
+pub struct ConditionsSerializer<'a> {
+	pub conditions: &'a [Box<dyn Condition>],
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a> Serialize for ConditionsSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let mut state = serializer.serialize_map(Some(self.conditions.len()))?;
+		for condition in self.conditions {
+			let condition = condition.as_reflect();
+			state.serialize_entry(
+				condition.reflect_type_path(),
+				&TypedReflectSerializer::new(condition, self.registry),
+			)?;
+		}
+		state.end()
+	}
+}
+
 #[derive(Resource, Debug, Reflect)]
 pub struct TimeLoop {
 	pub curr: T,
@@ -712,6 +1247,47 @@ pub struct TimeLoop {
 	pub resetting_to: LoopTime,
 }
 
+/// How fast the loop runs compared to real time.
+///
+/// Scales the loop clock, and with it [`Lifetime`]s and the clock hands, together with physics and
+/// animations. Everything else, like UI and input, keeps running in real time.
+#[derive(Resource, Copy, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct TimeScale {
+	/// Set by [`SetTimeScale`](crate::happens::SetTimeScale), and reset with the loop.
+	pub global: f32,
+	/// Product of the scales of every [`TimeDilationZone`] the player is in.
+	pub zones: f32,
+}
+
+impl Default for TimeScale {
+	fn default() -> Self {
+		Self {
+			global: 1.0,
+			zones: 1.0,
+		}
+	}
+}
+
+impl TimeScale {
+	pub fn get(&self) -> f32 {
+		(self.global * self.zones).max(0.0)
+	}
+}
+
+/// How far the loop clock advances this frame: the `Time<Virtual>` delta scaled by the
+/// [`TimeScale`].
+#[derive(Resource, Copy, Clone, Debug, Default, Deref)]
+pub struct LoopDelta(pub Duration);
+
+/// Scales time while the player is inside this entity's
+/// [`ColliderShape`](crate::data::phys::ColliderShape).
+#[derive(Component, Copy, Clone, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct TimeDilationZone {
+	pub scale: f32,
+}
+
 /// Mainly keeps timeline strong handles alive.
 #[derive(Resource, Deref, DerefMut)]
 pub struct LoadedTimelines(pub HashMap<AssetPath<'static>, Handle<Timeline>>);
@@ -725,6 +1301,7 @@ pub struct PortalPath {
 }
 
 /// Runtime portal reference.
+#[derive(Reflect, Copy, Clone, Debug)]
 pub struct Portal {
 	pub from: T,
 	pub to: T,
@@ -763,8 +1340,27 @@ impl AssetServerExt for AssetServer {
 	}
 }
 
+/// A resolved portal, inserted by [`resolve_portals`](crate::time_graph::resolve_portals).
 #[derive(Component, Debug, Reflect)]
-pub struct PortalTo(pub T);
+pub struct PortalTo {
+	pub portal: Portal,
+	pub exit: Option<Vec3>,
+	pub carry_offset: bool,
+}
+
+/// Scene component for a portal, resolved into a [`PortalTo`] once its timelines have been
+/// requested from the `AssetServer`.
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct LoadPortal {
+	pub path: PortalPath,
+	/// Where to move the player when they take the portal. They stay put if `None`.
+	#[serde(default)]
+	pub exit: Option<Vec3>,
+	/// Keep the player's offset from the portal when moving them to `exit`.
+	#[serde(default)]
+	pub carry_offset: bool,
+}
 
 #[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
 #[reflect(Component, Serialize, Deserialize)]
@@ -775,19 +1371,41 @@ pub struct Trigger {
 	pub causes: DoList,
 	#[serde(default)]
 	pub kind: TriggerKind,
+	/// What has to be inside the sensor to set it off. `Interact` triggers are always the player.
+	#[serde(default)]
+	pub by: Activator,
 }
 
 #[derive(Reflect, Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[reflect(Serialize, Deserialize)]
 pub enum TriggerKind {
+	/// Fires once each time something starts touching the sensor.
 	#[default]
 	Enter,
+	/// Fires once each time the last thing touching the sensor leaves.
+	Exit,
+	/// Fires once something has been touching the sensor for `after` without leaving.
+	Stay { after: LoopTime },
+	/// Fires on entering, then every `period` for as long as something is touching the sensor.
+	Cooldown { period: LoopTime },
 	Interact {
 		#[serde(default = "crate::data::ui::default_interact_msg")]
 		message: Str,
 	},
 }
 
+#[derive(Reflect, Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum Activator {
+	#[default]
+	Player,
+	/// Entities with this [`Name`], e.g. a box or one of the player's echoes.
+	Named(Str),
+	/// Anything with a dynamic or kinematic [`RigidBody`](bevy_xpbd_3d::prelude::RigidBody),
+	/// so static scenery doesn't count.
+	Any,
+}
+
 pub mod do_list_serde {
 	use super::{do_from_reflect, DoList};
 	use bevy::{
@@ -868,6 +1486,23 @@ pub fn do_from_reflect(
 	reflect_do.get_boxed(entry)
 }
 
+pub fn condition_from_reflect(
+	entry: Box<dyn Reflect>,
+	registry: &TypeRegistry,
+) -> Result<Box<dyn Condition>, Box<dyn Reflect>> {
+	let Some(type_info) = entry.get_represented_type_info() else {
+		return Err(entry);
+	};
+	let Some(registration) = registry.get(type_info.type_id()) else {
+		return Err(entry);
+	};
+	let Some(reflect_condition) = registration.data::<ReflectCondition>() else {
+		return Err(entry);
+	};
+
+	reflect_condition.get_boxed(entry)
+}
+
 pub fn do_ref_from_reflect<'a>(
 	entry: &'a dyn Reflect,
 	registry: &TypeRegistry,
@@ -0,0 +1,90 @@
+//! Numbers for designers to balance the player's movement without recompiling.
+
+use bevy::{
+	asset::{io::Reader, AssetLoader, AsyncReadExt, BoxedFuture, LoadContext},
+	prelude::*,
+	scene::SceneLoaderError,
+};
+use bevy_asset_loader::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct TuningDataPlugin;
+
+impl Plugin for TuningDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_asset::<PlayerTuning>()
+			.register_asset_loader(PlayerTuningLoader)
+			.register_type::<PlayerTuning>()
+			.register_type::<DashTuning>();
+	}
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct Tuning {
+	#[asset(path = "player.tuning.ron")]
+	pub player: Handle<PlayerTuning>,
+}
+
+#[derive(Asset, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerTuning {
+	pub dash: DashTuning,
+}
+
+#[derive(Clone, Debug, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashTuning {
+	/// How far a dash goes.
+	pub distance: f32,
+	/// Top speed during a dash.
+	pub speed: f32,
+	/// Speed the player is slowed down to once the dash is over.
+	pub brake_to_speed: f32,
+	pub acceleration: f32,
+	pub brake_acceleration: f32,
+	/// Time after starting a dash before the player can dash again.
+	#[serde(with = "crate::data::duration_str")]
+	pub cooldown: Duration,
+	/// Dashes allowed before landing again. 0 means dashing only works on the ground.
+	pub air_dashes: u32,
+}
+
+impl Default for DashTuning {
+	fn default() -> Self {
+		Self {
+			distance: 1.5,
+			speed: 12.0,
+			brake_to_speed: 2.0,
+			acceleration: 200.0,
+			brake_acceleration: 200.0,
+			cooldown: Duration::from_millis(600),
+			air_dashes: 1,
+		}
+	}
+}
+
+pub struct PlayerTuningLoader;
+
+impl AssetLoader for PlayerTuningLoader {
+	type Asset = PlayerTuning;
+	type Settings = ();
+	type Error = SceneLoaderError;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a Self::Settings,
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			Ok(ron::de::from_bytes::<PlayerTuning>(&bytes)?)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["tuning.ron"]
+	}
+}
@@ -1,4 +1,4 @@
-use crate::data::Str;
+use crate::{data::Str, player::Action};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +10,83 @@ pub struct InteractSign;
 #[reflect(Component, Serialize, Deserialize)]
 pub struct InteractText;
 
+/// The icon on the [`InteractSign`] for whatever `Interact` is bound to.
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct InteractImage;
+
+/// Name of the key or button shown on the [`InteractSign`] when it has no icon.
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct InteractKey;
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct PauseMenu;
+
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub enum PauseButton {
+	Resume,
+	Controls,
+	RestartLoop,
+	Quit,
+}
+
+impl PauseButton {
+	pub fn label(self) -> &'static str {
+		match self {
+			PauseButton::Resume => "Resume",
+			PauseButton::Controls => "Controls",
+			PauseButton::RestartLoop => "Restart loop",
+			PauseButton::Quit => "Quit",
+		}
+	}
+}
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct ControlsMenu;
+
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub enum ControlsButton {
+	Rebind(Action),
+	ResetDefaults,
+	Back,
+}
+
+/// Lists what an [`Action`] is bound to in the [`ControlsMenu`].
+#[derive(Component, Copy, Clone, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct BindingText(pub Action);
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct DialogueBox;
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct DialogueSpeaker;
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct DialogueText;
+
+/// Shown on lines the player has already read in an earlier loop.
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct DialogueSeenTag;
+
+#[derive(Component, Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct DialogueChoices;
+
+/// Picks the [`Choice`](crate::data::dlg::Choice) at this index of the current line.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct ChoiceButton(pub usize);
+
 pub fn default_interact_msg() -> Str {
 	"Interact".into()
 }
@@ -0,0 +1,219 @@
+//! Records the player during each loop and replays previous loops as translucent echoes.
+
+use crate::{
+	data::tl::{TimeLoop, T},
+	player::{
+		player_entity::{self, Root},
+		player_sprite, Action, PlayerAnimation, PlayerAnimationState,
+	},
+	GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::{
+	parry::shape::SharedShape,
+	prelude::{Collider, RigidBody, Sensor},
+};
+use leafwing_input_manager::prelude::ActionState;
+use sond_bevy_enum_components::WithVariant;
+
+/// How many previous loops to keep echoes of.
+pub const MAX_ECHOES: usize = 3;
+
+pub const ECHO_COLOR: Color = Color::rgba(0.6, 0.8, 1.0, 0.4);
+
+pub struct EchoPlugin;
+
+impl Plugin for EchoPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Recording>()
+			.init_resource::<Echoes>()
+			.add_event::<EchoAction>()
+			.add_systems(
+				OnEnter(GameState::ResettingLoop),
+				(archive_recording, despawn_echoes),
+			)
+			.add_systems(OnExit(GameState::ResettingLoop), spawn_echoes)
+			.add_systems(Update, replay_echoes.run_if(in_state(GameState::Running)))
+			.add_systems(
+				PostUpdate,
+				record_player.run_if(in_state(GameState::Running)),
+			);
+	}
+}
+
+/// The player's state during a single frame.
+#[derive(Clone, Debug)]
+pub struct EchoFrame {
+	pub t: T,
+	pub transform: Transform,
+	pub animation: PlayerAnimation,
+	pub atlas_index: usize,
+	/// Actions that were just pressed this frame.
+	pub pressed: Vec<Action>,
+}
+
+/// Frames recorded so far in the current loop.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct Recording(pub Vec<EchoFrame>);
+
+/// Recordings of previous loops, most recent first.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct Echoes(pub Vec<Vec<EchoFrame>>);
+
+/// Replays `Echoes[recording]`.
+#[derive(Component, Debug)]
+pub struct Echo {
+	pub recording: usize,
+	/// Index of the last frame that has been replayed.
+	pub cursor: Option<usize>,
+}
+
+#[derive(Component, Debug)]
+pub struct EchoSprite;
+
+/// Sent whenever an echo replays an action, so puzzles can react to past selves.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct EchoAction {
+	pub echo: Entity,
+	pub action: Action,
+}
+
+pub fn record_player(
+	mut recording: ResMut<Recording>,
+	tloop: Res<TimeLoop>,
+	player: Query<(&Transform, &ActionState<Action>), WithVariant<Root>>,
+	sprite: Query<(&PlayerAnimationState, &TextureAtlas), WithVariant<player_entity::Sprite>>,
+) {
+	let (Ok((xform, inputs)), Ok((anim, atlas))) = (player.get_single(), sprite.get_single())
+	else {
+		return;
+	};
+	let mut pressed = inputs.get_just_pressed();
+	pressed.retain(|action| *action != Action::Pause);
+	recording.push(EchoFrame {
+		t: tloop.curr,
+		transform: *xform,
+		animation: anim.curr_animation,
+		atlas_index: atlas.index,
+		pressed,
+	});
+}
+
+pub fn archive_recording(mut recording: ResMut<Recording>, mut echoes: ResMut<Echoes>) {
+	let frames = std::mem::take(&mut recording.0);
+	if frames.is_empty() {
+		return;
+	}
+	echoes.insert(0, frames);
+	echoes.truncate(MAX_ECHOES);
+}
+
+pub fn despawn_echoes(mut cmds: Commands, q: Query<Entity, With<Echo>>) {
+	for id in &q {
+		cmds.entity(id).despawn_recursive();
+	}
+}
+
+pub fn spawn_echoes(mut cmds: Commands, echoes: Res<Echoes>) {
+	for i in 0..echoes.len() {
+		// Older echoes are fainter
+		let fade = 1.0 - (i as f32 / MAX_ECHOES as f32);
+		let mut sprite = player_sprite();
+		sprite.material.base_color = ECHO_COLOR.with_a(ECHO_COLOR.a() * fade);
+		cmds.spawn((
+			Echo {
+				recording: i,
+				cursor: None,
+			},
+			Name::new(format!("Echo {i}")),
+			SpatialBundle {
+				visibility: Visibility::Hidden,
+				..default()
+			},
+			// Lets echoes set off triggers activated by their name or by anything.
+			RigidBody::Kinematic,
+			Collider::from(SharedShape::capsule_z(0.125, 0.25)),
+			Sensor,
+		))
+		.with_children(|cmds| {
+			cmds.spawn((sprite, EchoSprite));
+		});
+	}
+}
+
+pub fn replay_echoes(
+	echoes: Res<Echoes>,
+	tloop: Res<TimeLoop>,
+	mut q: Query<(
+		Entity,
+		&mut Echo,
+		&mut Transform,
+		&mut Visibility,
+		&Children,
+	)>,
+	mut sprites: Query<&mut TextureAtlas, With<EchoSprite>>,
+	mut events: EventWriter<EchoAction>,
+) {
+	let now = tloop.curr;
+	for (id, mut echo, mut xform, mut vis, children) in &mut q {
+		let Some(frames) = echoes.get(echo.recording) else {
+			continue;
+		};
+
+		// Time went backwards, e.g. through a portal. Catch up again without repeating actions.
+		let rewound = echo
+			.cursor
+			.and_then(|i| frames.get(i))
+			.is_some_and(|frame| frame.t.1 > now.1);
+		if rewound {
+			echo.cursor = None;
+		}
+		loop {
+			let next = echo.cursor.map_or(0, |i| i + 1);
+			let Some(frame) = frames.get(next) else {
+				break;
+			};
+			if frame.t.1 > now.1 {
+				break;
+			}
+			echo.cursor = Some(next);
+			if !rewound && frame.t.0 == now.0 {
+				for &action in &frame.pressed {
+					events.send(EchoAction { echo: id, action });
+				}
+			}
+		}
+
+		// Only shown while between two recorded frames in the current timeline.
+		let shown = echo
+			.cursor
+			.and_then(|i| Some((frames.get(i)?, frames.get(i + 1)?)))
+			.filter(|(frame, _)| frame.t.0 == now.0);
+		let Some((frame, next)) = shown else {
+			if *vis != Visibility::Hidden {
+				*vis = Visibility::Hidden;
+			}
+			continue;
+		};
+		if *vis != Visibility::Inherited {
+			*vis = Visibility::Inherited;
+		}
+		let span = (next.t.1 - frame.t.1).secs_f32();
+		let s = if next.t.0 == frame.t.0 && span > 0.0 {
+			((now.1 - frame.t.1).secs_f32() / span).clamp(0.0, 1.0)
+		} else {
+			0.0
+		};
+		xform.translation = frame
+			.transform
+			.translation
+			.lerp(next.transform.translation, s);
+		for child in children {
+			if let Ok(mut atlas) = sprites.get_mut(*child) {
+				if atlas.index != frame.atlas_index {
+					atlas.index = frame.atlas_index;
+				}
+			}
+		}
+	}
+}
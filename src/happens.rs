@@ -1,24 +1,29 @@
 use crate::{
 	data::{
+		area::{ActiveArea, AreaId},
+		dlg::ActiveDialogue,
+		flags::{Flag, FlagValue, WorldFlags},
 		phys::ColliderShape,
 		tl::{
-			Lifetime, LoadedTimelines, LoopTime, MomentRef, ReflectDo, SpawnedAt, TimeLoop,
-			Timeline, Trigger,
+			Lifetime, LoadPortal, LoadedTimelines, LoopTime, MomentRef, ReflectDo, SpawnedAt,
+			TimeLoop, TimeScale, Timeline, Trigger,
 		},
-		Str,
+		Str, SystemRegistry,
 	},
 	player::player_entity::Root,
+	save,
 	scn::Resettable,
-	GameState,
+	time_graph::{analysis::TimeGraph, reload::TimelineSnapshots, transition::start_transition},
 };
 use bevy::{
 	asset::{AssetPath, UntypedAssetId},
 	ecs::system::{Command, CommandQueue},
+	hierarchy::despawn_with_children_recursive,
 	prelude::*,
 	utils::HashMap,
 };
 use bevy_tnua::prelude::TnuaController;
-use bevy_xpbd_3d::prelude::{Collider, Sensor};
+use bevy_xpbd_3d::prelude::Sensor;
 use serde::{Deserialize, Serialize};
 use sond_bevy_enum_components::WithVariant;
 
@@ -28,12 +33,21 @@ impl Plugin for HappeningsPlugin {
 	fn build(&self, app: &mut App) {
 		app.register_type::<TakeBranch>()
 			.register_type::<SpawnTrigger>()
+			.register_type::<SpawnPortal>()
 			.register_type::<SpawnScene>()
 			.register_type::<SpawnDynamicScene>()
 			.register_type::<ModifyTimeline>()
 			.register_type::<Despawn>()
 			.register_type::<MovePlayerTo>()
-			.register_type::<ResetLoop>();
+			.register_type::<ResetLoop>()
+			.register_type::<SetTimeScale>()
+			.register_type::<ShowDialogue>()
+			.register_type::<SaveGame>()
+			.register_type::<LoadGame>()
+			.register_type::<SetFlag>()
+			.register_type::<TimestampFlag>()
+			.register_type::<IncrementFlag>()
+			.register_type::<RunSystem>();
 	}
 }
 
@@ -46,11 +60,6 @@ pub struct TakeBranch(pub AssetPath<'static>);
 impl Command for TakeBranch {
 	fn apply(self, world: &mut World) {
 		let branch_path = self.0.clone();
-		// let curr_id = world.resource::<TimeLoop>().curr.0;
-		// let timelines = world.resource::<Assets<Timeline>>();
-		// let curr_tl = timelines
-		// 	.get(curr_id)
-		// 	.expect("Can't be missing the current timeline");
 		let srv = world.resource::<AssetServer>();
 		let Some(branch_id) = srv
 			.get_path_id(branch_path.clone())
@@ -59,27 +68,19 @@ impl Command for TakeBranch {
 			error!("No `AssetId` for {branch_path}");
 			return;
 		};
-		// let Some(tl) = timelines.get(branch_id) else {
-		// 	error!("Timeline {branch_path} is not yet in the `Assets` resource");
-		// 	return;
-		// };
-
-		// TODO: To truly validate branches, would have to follow all branch/merge paths
-		//  until certain there's no way the new branch could be reached from the
-		//  current timeline.
-
-		// if tl.branch_from.map(|t| t.0) != Some(curr_id)
-		// && curr_tl.branch_from.map(|t| t.0) != Some(branch_id) {
-		// 	error!("Timeline {branch_path} does not branch from the current timeline");
-		// 	return;
-		// };
+		let curr = world.resource::<TimeLoop>().curr;
+		let graph = TimeGraph::new(world.resource::<Assets<Timeline>>(), srv);
+		if let Err(e) = graph.check_branch(curr, branch_id) {
+			error!("Refusing to take branch {branch_path}: {e}");
+			return;
+		}
 
 		world.resource_mut::<TimeLoop>().curr.0 = branch_id;
 	}
 }
 
-#[derive(Component, Default, Clone, Reflect, Deserialize)]
-#[reflect(Do, Deserialize)]
+#[derive(Component, Default, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
 #[serde(default)]
 #[type_path = "happens"]
 pub struct SpawnTrigger {
@@ -99,7 +100,7 @@ impl Command for SpawnTrigger {
 				local: self.transform,
 				global: self.global_transform,
 			},
-			Collider::from(self.sensor.clone()),
+			self.sensor,
 			Sensor,
 			self.trigger,
 			Resettable::default(),
@@ -114,6 +115,42 @@ impl Command for SpawnTrigger {
 	}
 }
 
+/// Spawns a portal sensor, which takes the player to `portal.path.to` when they enter it.
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct SpawnPortal {
+	#[serde(default)]
+	pub name: Option<Name>,
+	pub portal: LoadPortal,
+	#[serde(default)]
+	pub sensor: ColliderShape,
+	#[serde(default)]
+	pub transform: Transform,
+	#[serde(default)]
+	pub lifetime: Option<LoopTime>,
+}
+
+impl Command for SpawnPortal {
+	fn apply(self, world: &mut World) {
+		let timestamp = SpawnedAt(world.resource::<TimeLoop>().curr.1);
+		let mut cmds = world.spawn((
+			TransformBundle::from_transform(self.transform),
+			self.sensor,
+			Sensor,
+			self.portal,
+			Resettable::default(),
+			timestamp,
+		));
+		if let Some(name) = self.name {
+			cmds.insert(name);
+		}
+		if let Some(lt) = self.lifetime {
+			cmds.insert(Lifetime(lt));
+		}
+	}
+}
+
 #[derive(Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Do, Serialize, Deserialize)]
 #[type_path = "happens"]
@@ -158,10 +195,11 @@ impl Command for SpawnDynamicScene {
 #[reflect(Serialize, Deserialize, Do)]
 #[type_path = "happens"]
 #[serde(transparent)]
-pub struct ModifyTimeline(Vec<TimelineCommand>);
+pub struct ModifyTimeline(pub Vec<TimelineCommand>);
 
 impl Command for ModifyTimeline {
 	fn apply(self, world: &mut World) {
+		let mut edits = Vec::new();
 		for command in self.0 {
 			let srv = world.resource::<AssetServer>();
 			let Some(id) = srv.get_path_id(command.path.clone()) else {
@@ -180,7 +218,8 @@ impl Command for ModifyTimeline {
 					continue;
 				};
 				if let Some(setter) = update.disabled {
-					setter.apply_to(&mut moment.disabled)
+					setter.apply_to(&mut moment.disabled);
+					edits.push((id, moment.label, None, moment.disabled));
 				}
 				for (label, update) in update.happenings {
 					let Some(happenings) = moment
@@ -192,9 +231,16 @@ impl Command for ModifyTimeline {
 						continue;
 					};
 					update.apply_to(&mut happenings.disabled);
+					edits.push((id, moment.label, Some(label), happenings.disabled));
 				}
 			}
 		}
+		// Remembered so hot-reloading the timeline doesn't lose them.
+		if let Some(mut snapshots) = world.get_resource_mut::<TimelineSnapshots>() {
+			for (id, moment, happenings, disabled) in edits {
+				snapshots.record_edit(id, moment, happenings, disabled);
+			}
+		}
 	}
 }
 
@@ -305,33 +351,271 @@ impl Command for MovePlayerTo {
 #[serde(default)]
 pub struct ResetLoop {
 	pub to: LoopTime,
+	/// Name of a [`LoopTransition`](crate::time_graph::transition::LoopTransition) in
+	/// [`LoopTransitions`](crate::time_graph::transition::LoopTransitions), e.g. `"instant"`.
+	pub transition: Option<Str>,
 }
 
 impl Command for ResetLoop {
 	fn apply(self, world: &mut World) {
-		let mut tloop = world.resource_mut::<TimeLoop>();
-		let curr = tloop.curr.1;
-		tloop.resetting_from = curr;
-		tloop.resetting_to = self.to;
-		world
-			.resource_mut::<NextState<GameState>>()
-			.set(GameState::ResettingLoop);
+		start_transition(world, self.to, self.transition);
 		let mut q = world.query::<&mut TnuaController>();
 		let mut ctrl = q.single_mut(world);
 		ctrl.neutralize_basis();
 	}
 }
 
+/// Sets [`TimeScale::global`] until the loop is reset.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct SetTimeScale {
+	pub scale: f32,
+}
+
+impl Command for SetTimeScale {
+	fn apply(self, world: &mut World) {
+		if !self.scale.is_finite() || self.scale < 0.0 {
+			error!("Invalid time scale {}", self.scale);
+			return;
+		}
+		world.resource_mut::<TimeScale>().global = self.scale;
+	}
+}
+
+/// Opens the dialogue box with a `.dlg.ron` file, replacing any dialogue already showing.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct ShowDialogue {
+	pub path: AssetPath<'static>,
+	/// Label of the line to start from instead of the first.
+	#[serde(default)]
+	pub start: Option<Str>,
+}
+
+impl Command for ShowDialogue {
+	fn apply(self, world: &mut World) {
+		let handle = world.resource::<AssetServer>().load(self.path.clone());
+		world.insert_resource(ActiveDialogue {
+			path: self.path,
+			handle,
+			start: self.start,
+			line: None,
+			revealed: 0.0,
+			seen: false,
+		});
+	}
+}
+
+/// Saves to the given slot, or the autosave slot if empty.
+#[derive(Default, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+#[serde(default)]
+pub struct SaveGame {
+	pub slot: Option<Str>,
+}
+
+impl Command for SaveGame {
+	fn apply(self, world: &mut World) {
+		save::save_game(world, self.slot.map_or(save::AUTOSAVE, |slot| slot.0 .0))
+	}
+}
+
+/// Loads from the given slot, or the autosave slot if empty.
+#[derive(Default, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+#[serde(default)]
+pub struct LoadGame {
+	pub slot: Option<Str>,
+}
+
+impl Command for LoadGame {
+	fn apply(self, world: &mut World) {
+		save::load_game(world, self.slot.map_or(save::AUTOSAVE, |slot| slot.0 .0))
+	}
+}
+
+/// Sets a [`WorldFlags`] entry.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct SetFlag {
+	pub flag: Str,
+	#[serde(default)]
+	pub value: FlagValue,
+	/// Whether the flag survives [`ResetLoop`].
+	#[serde(default)]
+	pub persistent: bool,
+}
+
+impl Command for SetFlag {
+	fn apply(self, world: &mut World) {
+		world.resource_mut::<WorldFlags>().insert(
+			self.flag,
+			Flag {
+				value: self.value,
+				persistent: self.persistent,
+			},
+		);
+	}
+}
+
+/// Sets a [`WorldFlags`] entry to the current [`LoopTime`].
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct TimestampFlag {
+	pub flag: Str,
+	#[serde(default)]
+	pub persistent: bool,
+}
+
+impl Command for TimestampFlag {
+	fn apply(self, world: &mut World) {
+		let now = world.resource::<TimeLoop>().curr.1;
+		world.resource_mut::<WorldFlags>().insert(
+			self.flag,
+			Flag {
+				value: FlagValue::Time(now),
+				persistent: self.persistent,
+			},
+		);
+	}
+}
+
+/// Adds `by` to an `Int` [`WorldFlags`] entry, starting from 0 if it isn't set.
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+pub struct IncrementFlag {
+	pub flag: Str,
+	#[serde(default = "_one")]
+	pub by: i64,
+	/// Whether the flag survives [`ResetLoop`], if it isn't set yet. An existing flag keeps its
+	/// own.
+	#[serde(default)]
+	pub persistent: bool,
+}
+
+fn _one() -> i64 {
+	1
+}
+
+impl Command for IncrementFlag {
+	fn apply(self, world: &mut World) {
+		let mut flags = world.resource_mut::<WorldFlags>();
+		let flag = flags.entry(self.flag).or_insert(Flag {
+			value: FlagValue::Int(0),
+			persistent: self.persistent,
+		});
+		match &mut flag.value {
+			FlagValue::Int(n) => *n += self.by,
+			other => error!(
+				"Can't increment flag {}: {other:?} is not an `Int`",
+				self.flag
+			),
+		}
+	}
+}
+
+/// Runs a one-shot system registered with
+/// [`register_named_system`](crate::data::RegisterNamedSystem::register_named_system).
+#[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Do, Serialize, Deserialize)]
+#[type_path = "happens"]
+#[serde(transparent)]
+pub struct RunSystem(pub Str);
+
+impl Command for RunSystem {
+	fn apply(self, world: &mut World) {
+		let Some(id) = world
+			.get_resource::<SystemRegistry>()
+			.and_then(|registry| registry.get(&self.0).copied())
+		else {
+			error!("No system named {}", self.0);
+			return;
+		};
+		if let Err(e) = world.run_system(id) {
+			error!("Failed to run system {}: {e}", self.0);
+		}
+	}
+}
+
 pub fn reset_world(world: &mut World) {
+	reload_timelines(world);
+	reload_active_area(world);
+	reset_entities(world);
+	reset_flags(world);
+	reset_time_scale(world);
+	close_dialogue(world);
+}
+
+/// Forgets [`WorldFlags`] that don't survive the loop.
+pub fn reset_flags(world: &mut World) {
+	if let Some(mut flags) = world.get_resource_mut::<WorldFlags>() {
+		flags.reset_loop();
+	}
+}
+
+/// Closes any dialogue, since what it was about may not have happened yet.
+pub fn close_dialogue(world: &mut World) {
+	world.remove_resource::<ActiveDialogue>();
+}
+
+/// Puts [`TimeScale::global`] back to normal speed.
+pub fn reset_time_scale(world: &mut World) {
+	if let Some(mut scale) = world.get_resource_mut::<TimeScale>() {
+		scale.global = 1.0;
+	}
+}
+
+/// Discards runtime edits to timelines.
+pub fn reload_timelines(world: &mut World) {
+	// Reloading is what undoes runtime edits, so don't restore them afterwards.
+	if let Some(mut snapshots) = world.get_resource_mut::<TimelineSnapshots>() {
+		snapshots.clear_edits();
+	}
 	let timelines = world.resource::<LoadedTimelines>();
 	let srv = world.resource::<AssetServer>();
 	for path in timelines.keys() {
 		srv.reload(path)
 	}
-	let mut q = world.query::<(Entity, &Resettable)>();
+}
+
+/// Despawns the active area so it is streamed back in fresh from its scene.
+pub fn reload_active_area(world: &mut World) {
+	let Some(area) = world
+		.get_resource::<ActiveArea>()
+		.and_then(|active| active.0)
+	else {
+		return;
+	};
+	// Children are despawned along with their parents, including the scene's root.
+	let mut q = world.query_filtered::<(Entity, &AreaId), Without<Parent>>();
+	let owned = q
+		.iter(world)
+		.filter_map(|(id, owner)| (owner.0 == area).then_some(id))
+		.collect::<Vec<_>>();
+	for id in owned {
+		despawn_with_children_recursive(world, id);
+	}
+}
+
+/// Runs the resetter of every [`Resettable`] entity that is global or in the active area.
+pub fn reset_entities(world: &mut World) {
+	let active = world
+		.get_resource::<ActiveArea>()
+		.and_then(|active| active.0);
+	let mut q = world.query::<(Entity, &Resettable, Option<&AreaId>)>();
 	let mut queue = CommandQueue::default();
 	let mut cmds = Commands::new(&mut queue, &*world);
-	for (id, reset) in q.iter(world) {
+	for (id, reset, area) in q.iter(world) {
+		if area.map_or(false, |area| Some(area.0) != active) {
+			continue;
+		}
 		let cmds = cmds.entity(id);
 		reset.defer_reset(cmds);
 	}
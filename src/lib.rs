@@ -1,23 +1,47 @@
 use crate::{
 	cam::CamPlugin,
-	data::{tl::Timelines, SystemRegistry},
+	conditions::ConditionsPlugin,
+	data::{
+		area::Areas,
+		dlg::DialogueDataPlugin,
+		flags::FlagDataPlugin,
+		tl::{TimeDataPlugin, Timelines},
+		tuning::Tuning,
+		SystemRegistry,
+	},
+	echo::EchoPlugin,
 	happens::HappeningsPlugin,
-	player::PlayerPlugin,
-	scn::{clock::tick_hand, EnvironmentPlugin},
-	ui::GameUiPlugin,
+	player::{player_entity::Root, Action, PlayerPlugin},
+	save::SavePlugin,
+	scn::{
+		clock::tick_hand,
+		intro::{BreakClock, FlipLever, OpenPanel, RaiseWalls},
+		EnvironmentPlugin,
+	},
+	ui::{controls::Rebinding, GameUiPlugin},
 };
 use bevy::{asset::AssetMetaCheck, prelude::*, reflect::TypeRegistryArc};
 use bevy_asset_loader::prelude::*;
-use bevy_xpbd_3d::{plugins::PhysicsPlugins, prelude::Gravity};
+use bevy_xpbd_3d::{
+	plugins::PhysicsPlugins,
+	prelude::{Gravity, Physics, PhysicsTime},
+};
 use data::DataPlugin;
+use leafwing_input_manager::prelude::ActionState;
+use sond_bevy_enum_components::WithVariant;
 use std::sync::OnceLock;
 use time_graph::TimeGraphPlugin;
 
 pub mod cam;
+pub mod conditions;
 pub mod data;
+pub mod echo;
 pub mod happens;
 pub mod player;
+pub mod save;
 pub mod scn;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod time_graph;
 pub mod ui;
 pub mod util;
@@ -48,7 +72,9 @@ impl Plugin for GamePlugin {
 		app.init_state::<GameState>().add_loading_state(
 			LoadingState::new(GameState::Loading)
 				.continue_to_state(GameState::Running)
-				.load_collection::<Timelines>(),
+				.load_collection::<Timelines>()
+				.load_collection::<Tuning>()
+				.load_collection::<Areas>(),
 		);
 
 		// Dependencies
@@ -86,13 +112,27 @@ impl Plugin for GamePlugin {
 			DataPlugin,
 			CamPlugin,
 			HappeningsPlugin,
+			ConditionsPlugin,
 			TimeGraphPlugin,
 			PlayerPlugin,
 			EnvironmentPlugin,
 			GameUiPlugin,
+			SavePlugin,
+			EchoPlugin,
 		))
 		.add_systems(Startup, setup)
-		.add_systems(Update, tick_hand);
+		.add_systems(
+			Update,
+			(
+				tick_hand,
+				toggle_pause
+					.run_if(in_state(GameState::Running).or_else(in_state(GameState::Paused)))
+					// Pause keys can be rebound too.
+					.run_if(not(resource_exists::<Rebinding>)),
+			),
+		)
+		.add_systems(OnEnter(GameState::Paused), pause_physics)
+		.add_systems(OnExit(GameState::Paused), resume_physics);
 
 		#[cfg(feature = "debugging")]
 		app.add_plugins(bevy_xpbd_3d::plugins::PhysicsDebugPlugin::default())
@@ -107,6 +147,26 @@ impl Plugin for GamePlugin {
 	}
 }
 
+/// Registers everything needed to load timelines, without any systems that need a window or
+/// renderer. Meant for tools and tests rather than the game itself.
+pub struct HeadlessDataPlugin;
+
+impl Plugin for HeadlessDataPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_type::<RaiseWalls>()
+			.register_type::<FlipLever>()
+			.register_type::<OpenPanel>()
+			.register_type::<BreakClock>()
+			.add_plugins((
+				TimeDataPlugin,
+				FlagDataPlugin,
+				DialogueDataPlugin,
+				HappeningsPlugin,
+				ConditionsPlugin,
+			));
+	}
+}
+
 #[derive(Resource, Deref, DerefMut)]
 pub struct GlobalsScene(pub Handle<DynamicScene>);
 
@@ -120,6 +180,32 @@ pub fn setup(
 	scene_spawner.spawn_dynamic(globals_scene);
 }
 
+pub fn toggle_pause(
+	player: Query<&ActionState<Action>, WithVariant<Root>>,
+	state: Res<State<GameState>>,
+	mut next_state: ResMut<NextState<GameState>>,
+) {
+	let Ok(inputs) = player.get_single() else {
+		return;
+	};
+	if !inputs.just_pressed(&Action::Pause) {
+		return;
+	}
+	match state.get() {
+		GameState::Running => next_state.set(GameState::Paused),
+		GameState::Paused => next_state.set(GameState::Running),
+		_ => {}
+	}
+}
+
+pub fn pause_physics(mut time: ResMut<Time<Physics>>) {
+	time.pause();
+}
+
+pub fn resume_physics(mut time: ResMut<Time<Physics>>) {
+	time.unpause();
+}
+
 #[cfg(feature = "debugging")]
 pub fn toggle_phys_gizmos(mut store: ResMut<GizmoConfigStore>, keys: Res<ButtonInput<KeyCode>>) {
 	if keys.just_pressed(KeyCode::KeyG) {
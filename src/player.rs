@@ -2,16 +2,19 @@ use crate::{
 	data::{
 		cam::cam_node,
 		sprites::{LoadAtlas3d, LoadSprite3d},
+		tl::LoopDelta,
+		tuning::{PlayerTuning, Tuning},
 		LoadAlphaMode, LoadStdMat,
 	},
 	player::player_entity::WithPlayerEntity,
+	save::storage,
 	GameState,
 };
 use bevy::prelude::*;
 use bevy_tnua::{
 	controller::{TnuaController, TnuaControllerPlugin},
-	prelude::{TnuaBuiltinJump, TnuaBuiltinWalk, TnuaControllerBundle},
-	TnuaProximitySensor,
+	prelude::{TnuaBuiltinDash, TnuaBuiltinJump, TnuaBuiltinWalk, TnuaControllerBundle},
+	TnuaAction, TnuaProximitySensor,
 };
 use bevy_tnua_xpbd3d::{TnuaXpbd3dPlugin, TnuaXpbd3dSensorShape};
 use bevy_xpbd_3d::{
@@ -40,7 +43,7 @@ impl Plugin for PlayerPlugin {
 			Update,
 			(
 				move_player.run_if(in_state(GameState::Running)),
-				animate_player,
+				animate_player.run_if(not(in_state(GameState::Paused))),
 			),
 		);
 	}
@@ -56,17 +59,7 @@ pub enum PlayerEntity {
 }
 
 pub fn spawn_player(mut cmds: Commands) {
-	let input_map = InputMap::new([
-		(Action::Move, UserInput::from(VirtualDPad::wasd())),
-		(Action::Move, VirtualDPad::arrow_keys().into()),
-		(Action::Move, DualAxis::left_stick().into()),
-		(Action::Jump, GamepadButtonType::South.into()),
-		(Action::Jump, KeyCode::Space.into()),
-		(Action::Jump, KeyCode::Backspace.into()),
-		(Action::Dash, GamepadButtonType::RightTrigger2.into()),
-		(Action::Interact, KeyCode::KeyE.into()),
-		(Action::Interact, GamepadButtonType::East.into()),
-	]);
+	let input_map = load_input_map();
 
 	cmds.spawn((
 		TransformBundle {
@@ -89,40 +82,13 @@ pub fn spawn_player(mut cmds: Commands) {
 		TnuaXpbd3dSensorShape(Collider::sphere(0.2)),
 		InputManagerBundle::with_map(input_map),
 		LockedAxes::ROTATION_LOCKED,
+		PlayerDash::default(),
+		Facing::default(),
 	))
 	.with_enum(player_entity::Root)
 	.with_children(|cmds| {
 		cmds.spawn((
-			LoadSprite3d {
-				transform: Transform {
-					translation: Vec3 {
-						x: 0.0,
-						// Nudge towards camera to align feet with far edge of platforms before falling off.
-						y: -0.125,
-						// Nudge down to compensate for float height.
-						z: -0.125,
-					},
-					..default()
-				},
-				size: Vec2::new(0.5, 1.0),
-				atlas_layout: Some(LoadAtlas3d {
-					tile_size: Vec2::new(256.0, 512.0),
-					columns: 4,
-					rows: 4,
-					padding: None,
-					offset: None,
-				}),
-				material: LoadStdMat {
-					base_color_texture: Some("player.png".into()),
-					alpha_mode: LoadAlphaMode::Blend,
-					perceptual_roughness: 1.0,
-					reflectance: 0.0,
-					double_sided: true,
-					cull_mode: None,
-					..default()
-				},
-				..default()
-			},
+			player_sprite(),
 			PlayerAnimationState {
 				timer: Timer::new(Duration::from_millis(350), TimerMode::Repeating),
 				curr_animation: default(),
@@ -132,6 +98,108 @@ pub fn spawn_player(mut cmds: Commands) {
 	});
 }
 
+/// Name of the [`storage`] entry holding the player's controls.
+pub const CONTROLS: &str = "controls";
+
+pub fn default_input_map() -> InputMap<Action> {
+	InputMap::new([
+		(Action::Move, UserInput::from(VirtualDPad::wasd())),
+		(Action::Move, VirtualDPad::arrow_keys().into()),
+		(Action::Move, DualAxis::left_stick().into()),
+		(Action::Jump, GamepadButtonType::South.into()),
+		(Action::Jump, KeyCode::Space.into()),
+		(Action::Jump, KeyCode::Backspace.into()),
+		(Action::Dash, GamepadButtonType::RightTrigger2.into()),
+		(Action::Dash, KeyCode::ShiftLeft.into()),
+		(Action::Interact, KeyCode::KeyE.into()),
+		(Action::Interact, GamepadButtonType::East.into()),
+		(Action::CycleInteract, KeyCode::Tab.into()),
+		(Action::CycleInteract, GamepadButtonType::North.into()),
+		(Action::Pause, KeyCode::Escape.into()),
+		(Action::Pause, GamepadButtonType::Start.into()),
+	])
+}
+
+/// The saved controls, or the defaults if there aren't any or they can't be read.
+pub fn load_input_map() -> InputMap<Action> {
+	let ron = match storage::read(CONTROLS) {
+		Ok(Some(ron)) => ron,
+		Ok(None) => return default_input_map(),
+		Err(e) => {
+			error!("Failed to read controls: {e}");
+			return default_input_map();
+		}
+	};
+	match ron::from_str(&ron) {
+		Ok(mut map) => {
+			add_missing_bindings(&mut map);
+			map
+		}
+		Err(e) => {
+			error!("Failed to parse controls: {e}");
+			default_input_map()
+		}
+	}
+}
+
+/// Gives actions added since the controls were saved their default bindings.
+fn add_missing_bindings(map: &mut InputMap<Action>) {
+	for (action, inputs) in default_input_map().iter() {
+		if map.get(action).map_or(true, Vec::is_empty) {
+			for input in inputs {
+				map.insert(*action, input.clone());
+			}
+		}
+	}
+}
+
+pub fn save_input_map(map: &InputMap<Action>) {
+	let ron = match ron::ser::to_string_pretty(map, default()) {
+		Ok(ron) => ron,
+		Err(e) => {
+			error!("Failed to serialize controls: {e}");
+			return;
+		}
+	};
+	if let Err(e) = storage::write(CONTROLS, &ron) {
+		error!("Failed to write controls: {e}");
+	}
+}
+
+/// The sprite shared by the player and their [`Echo`](crate::echo::Echo)es.
+pub fn player_sprite() -> LoadSprite3d {
+	LoadSprite3d {
+		transform: Transform {
+			translation: Vec3 {
+				x: 0.0,
+				// Nudge towards camera to align feet with far edge of platforms before falling off.
+				y: -0.125,
+				// Nudge down to compensate for float height.
+				z: -0.125,
+			},
+			..default()
+		},
+		size: Vec2::new(0.5, 1.0),
+		atlas_layout: Some(LoadAtlas3d {
+			tile_size: Vec2::new(256.0, 512.0),
+			columns: 4,
+			rows: 4,
+			padding: None,
+			offset: None,
+		}),
+		material: LoadStdMat {
+			base_color_texture: Some("player.png".into()),
+			alpha_mode: LoadAlphaMode::Blend,
+			perceptual_roughness: 1.0,
+			reflectance: 0.0,
+			double_sided: true,
+			cull_mode: None,
+			..default()
+		},
+		..default()
+	}
+}
+
 #[derive(Debug, Actionlike, Copy, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 #[reflect(Serialize, Deserialize)]
 pub enum Action {
@@ -139,18 +207,65 @@ pub enum Action {
 	Jump,
 	Dash,
 	Interact,
+	/// Moves interaction focus to the next trigger in reach.
+	CycleInteract,
+	Pause,
+}
+
+/// Cooldown and air dashes left for the player's dash. Tuned by [`DashTuning`].
+///
+/// [`DashTuning`]: crate::data::tuning::DashTuning
+#[derive(Component, Debug)]
+pub struct PlayerDash {
+	pub cooldown: Timer,
+	/// Dashes used since last standing on something.
+	pub air_dashes: u32,
+}
+
+impl Default for PlayerDash {
+	fn default() -> Self {
+		let mut cooldown = Timer::new(Duration::ZERO, TimerMode::Once);
+		cooldown.tick(Duration::ZERO);
+		Self {
+			cooldown,
+			air_dashes: 0,
+		}
+	}
+}
+
+/// The direction the player last moved in, on the XY plane.
+#[derive(Component, Copy, Clone, Debug, Deref, DerefMut)]
+pub struct Facing(pub Vec2);
+
+impl Default for Facing {
+	fn default() -> Self {
+		Self(Vec2::NEG_Y)
+	}
 }
 
 pub fn move_player(
-	mut q: Query<(Entity, &mut TnuaController, &ActionState<Action>)>,
+	mut q: Query<(
+		Entity,
+		&mut TnuaController,
+		&ActionState<Action>,
+		&mut PlayerDash,
+		&mut Facing,
+	)>,
 	mut anim_q: Query<(&mut PlayerAnimationState, &Parent)>,
 	mut cam_q: Query<&mut Transform, WithVariant<cam_node::Anchor>>,
+	tuning: Res<Tuning>,
+	tunings: Res<Assets<PlayerTuning>>,
 	t: Res<Time>,
 ) {
-	for (id, mut ctrl, action_state) in &mut q {
+	let default_tuning = PlayerTuning::default();
+	let tuning = tunings.get(&tuning.player).unwrap_or(&default_tuning);
+	for (id, mut ctrl, action_state, mut dash, mut facing) in &mut q {
 		let v = action_state
 			.clamped_axis_pair(&Action::Move)
 			.map_or(Vec2::ZERO, |data| data.xy() * 2.0);
+		if v.length() > 0.2 {
+			**facing = v.normalize();
+		}
 
 		ctrl.basis(TnuaBuiltinWalk {
 			desired_velocity: Vec3::new(v.x, v.y, 0.0),
@@ -186,7 +301,11 @@ pub fn move_player(
 				} else if v.angle_between(Vec2::X).abs() < FRAC_PI_4 {
 					anim_state.curr_animation = Right
 				};
-				if v.length() > 0.5 {
+				// `player.png` has no dash row, so dashing plays the walk cycle for its direction
+				// at a sprint.
+				if ctrl.action_name() == Some(TnuaBuiltinDash::NAME) {
+					anim_state.timer.set_duration(Duration::from_millis(80))
+				} else if v.length() > 0.5 {
 					anim_state.timer.set_duration(Duration::from_millis(200))
 				} else {
 					anim_state.timer.set_duration(Duration::from_millis(350))
@@ -202,6 +321,33 @@ pub fn move_player(
 				..default()
 			});
 		}
+
+		let dash_tuning = &tuning.dash;
+		dash.cooldown.tick(t.delta());
+		let airborne = ctrl.is_airborne().unwrap_or(false);
+		if !airborne {
+			dash.air_dashes = 0;
+		}
+		if action_state.just_pressed(&Action::Dash)
+			&& dash.cooldown.finished()
+			&& (!airborne || dash.air_dashes < dash_tuning.air_dashes)
+		{
+			if airborne {
+				dash.air_dashes += 1;
+			}
+			dash.cooldown = Timer::new(dash_tuning.cooldown, TimerMode::Once);
+			let dir = facing.extend(0.0);
+			ctrl.action(TnuaBuiltinDash {
+				displacement: dir * dash_tuning.distance,
+				desired_forward: dir,
+				allow_in_air: true,
+				speed: dash_tuning.speed,
+				brake_to_speed: dash_tuning.brake_to_speed,
+				acceleration: dash_tuning.acceleration,
+				brake_acceleration: dash_tuning.brake_acceleration,
+				..default()
+			});
+		}
 	}
 }
 
@@ -210,10 +356,10 @@ pub fn animate_player(
 		(&mut TextureAtlas, &mut PlayerAnimationState),
 		WithVariant<player_entity::Sprite>,
 	>,
-	t: Res<Time>,
+	delta: Res<LoopDelta>,
 ) {
 	for (mut atlas, mut state) in &mut q {
-		state.timer.tick(t.delta());
+		state.timer.tick(**delta);
 		let new = if state.timer.just_finished() {
 			atlas.index + 1
 		} else {
@@ -232,7 +378,7 @@ pub struct PlayerAnimationState {
 	pub curr_animation: PlayerAnimation,
 }
 
-#[derive(Copy, Clone, Default, Debug)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
 #[repr(usize)]
 pub enum PlayerAnimation {
 	#[default]
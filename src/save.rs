@@ -0,0 +1,569 @@
+//! Persists the time loop and runtime timeline edits between sessions.
+
+use crate::{
+	data::{
+		area::{ActiveArea, AreaId},
+		dlg::SeenDialogue,
+		flags::WorldFlags,
+		tl::{
+			init_time_loop, AssetServerExt, LoadedTimelines, LoopTime, Moment, TPath, TimeLoop,
+			Timeline,
+		},
+		Str,
+	},
+	player::player_entity::Root,
+	scn::{
+		area::{stream_areas, tag_area_entities, AreaRoot},
+		Resettable,
+	},
+	GameState,
+};
+use bevy::{
+	app::AppExit,
+	asset::AssetPath,
+	ecs::reflect::ReflectComponent,
+	prelude::*,
+	reflect::TypeRegistry,
+	scene::{
+		serde::{SceneDeserializer, SceneSerializer},
+		DynamicSceneBuilder,
+	},
+	utils::HashMap,
+};
+use serde::{
+	de::{DeserializeSeed, Error, MapAccess, Visitor},
+	ser::SerializeStruct,
+	Deserialize, Deserializer, Serialize, Serializer,
+};
+use sond_bevy_enum_components::WithVariant;
+use std::{collections::BTreeMap, fmt::Formatter, time::Duration};
+
+pub mod storage;
+
+/// Name of the save slot used for autosaves.
+pub const AUTOSAVE: &str = "autosave";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(AutoSave(Timer::new(
+			Duration::from_secs(30),
+			TimerMode::Repeating,
+		)))
+		.add_systems(
+			OnExit(GameState::Loading),
+			load_autosave.after(init_time_loop),
+		)
+		.add_systems(
+			Update,
+			(
+				place_saved_player.before(stream_areas),
+				restore_saved_entities
+					.after(tag_area_entities)
+					.run_if(active_area_spawned),
+			)
+				.run_if(resource_exists::<SavedEntities>)
+				.run_if(in_state(GameState::Running)),
+		)
+		.add_systems(
+			Last,
+			(autosave.run_if(in_state(GameState::Running)), save_on_exit),
+		);
+	}
+}
+
+/// How often to autosave, since web builds never get an [`AppExit`].
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct AutoSave(pub Timer);
+
+/// Everything in a save file except for the [`Resettable`] entities.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveData {
+	pub curr: TPath,
+	#[serde(default)]
+	pub player: Option<Vec3>,
+	/// Runtime edits made by [`ModifyTimeline`](crate::happens::ModifyTimeline).
+	#[serde(default)]
+	pub timelines: HashMap<AssetPath<'static>, BTreeMap<LoopTime, MomentFlags>>,
+	#[serde(default)]
+	pub flags: WorldFlags,
+	#[serde(default)]
+	pub seen_dialogue: SeenDialogue,
+}
+
+/// `disabled` flags for a [`Moment`](crate::data::tl::Moment) and each of its `happenings`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MomentFlags {
+	pub disabled: bool,
+	/// Flags of labelled happenings, which still find their group if others are added or
+	/// reordered.
+	pub labelled: HashMap<Str, bool>,
+	/// Flags of unlabelled happenings in order, only restored if the moment still has as many.
+	pub happenings: Vec<bool>,
+}
+
+/// A [`SaveData`] plus the state of all named [`Resettable`] entities.
+///
+/// Entities are matched up by [`Name`] when loading, so unnamed ones aren't saved at all and
+/// come back the way their scene spawns them.
+pub struct SaveFile {
+	pub data: SaveData,
+	pub world: DynamicScene,
+}
+
+impl SaveFile {
+	/// Takes a snapshot of the current world.
+	///
+	/// Only the [`Name`], [`Transform`] and [`Visibility`] of [`Resettable`] entities are saved,
+	/// since other components, like colliders and triggers, don't survive being serialized.
+	/// Entities without a `Name` can't be found again when loading, so they aren't saved at all.
+	///
+	/// Returns `None` if the current timeline doesn't have a path, e.g. before it has loaded.
+	pub fn capture(world: &mut World) -> Option<Self> {
+		let curr = world.resource::<TimeLoop>().curr;
+		let Some(curr) = world.resource::<AssetServer>().t_path_for_t(curr) else {
+			error!("No path for current timeline {:?}", curr.0);
+			return None;
+		};
+
+		let loaded = world.resource::<LoadedTimelines>();
+		let assets = world.resource::<Assets<Timeline>>();
+		let timelines = loaded
+			.iter()
+			.filter_map(|(path, handle)| {
+				let tl = assets.get(handle)?;
+				let flags = tl
+					.moments
+					.iter()
+					.map(|(t, moment)| {
+						let mut flags = MomentFlags {
+							disabled: moment.disabled,
+							..default()
+						};
+						for h in &moment.happenings {
+							match h.label {
+								Some(label) => {
+									flags.labelled.insert(label, h.disabled);
+								}
+								None => flags.happenings.push(h.disabled),
+							}
+						}
+						(*t, flags)
+					})
+					.collect();
+				Some((path.clone(), flags))
+			})
+			.collect();
+
+		let flags = world
+			.get_resource::<WorldFlags>()
+			.cloned()
+			.unwrap_or_default();
+
+		let seen_dialogue = world
+			.get_resource::<SeenDialogue>()
+			.cloned()
+			.unwrap_or_default();
+
+		let player = world
+			.query_filtered::<&Transform, WithVariant<Root>>()
+			.get_single(world)
+			.ok()
+			.map(|xform| xform.translation);
+
+		let (named, unnamed) = world
+			.query_filtered::<(Entity, Option<&Name>), With<Resettable>>()
+			.iter(world)
+			.partition::<Vec<_>, _>(|(_, name)| name.is_some());
+		if !unnamed.is_empty() {
+			debug!(
+				"Not saving {} `Resettable` entities without a `Name`",
+				unnamed.len()
+			);
+		}
+		let scene = DynamicSceneBuilder::from_world(world)
+			.allow::<Name>()
+			.allow::<Transform>()
+			.allow::<Visibility>()
+			.extract_entities(named.into_iter().map(|(id, _)| id))
+			.build();
+
+		Some(Self {
+			data: SaveData {
+				curr,
+				player,
+				timelines,
+				flags,
+				seen_dialogue,
+			},
+			world: scene,
+		})
+	}
+
+	/// Puts the loop and timelines back the way they were saved.
+	///
+	/// The player and entities haven't spawned yet when the autosave is loaded, so they're left in
+	/// a [`SavedEntities`] to be restored once they have.
+	pub fn restore(self, world: &mut World) {
+		let SaveFile { data, world: scene } = self;
+
+		let srv = world.resource::<AssetServer>().clone();
+		let Some(curr) = srv.t_for_t_path(data.curr.clone()) else {
+			error!("Saved timeline {} is not loaded", data.curr.0);
+			return;
+		};
+		let mut tloop = world.resource_mut::<TimeLoop>();
+		tloop.curr = curr;
+		tloop.resetting_from = curr.1;
+		tloop.resetting_to = curr.1;
+
+		let mut assets = world.resource_mut::<Assets<Timeline>>();
+		for (path, moments) in data.timelines {
+			let Some(tl) = srv
+				.get_path_id(path.clone())
+				.and_then(|id| assets.get_mut(id.typed::<Timeline>()))
+			else {
+				warn!("Saved timeline {path} is not loaded");
+				continue;
+			};
+			for (t, flags) in moments {
+				let Some(moment) = tl.moments.get_mut(&t) else {
+					warn!("{path} no longer has a moment at {t}");
+					continue;
+				};
+				moment.disabled = flags.disabled;
+				restore_happenings_flags(moment, flags, &path, t);
+			}
+		}
+
+		world.insert_resource(data.flags);
+		world.insert_resource(data.seen_dialogue);
+		world.insert_resource(SavedEntities {
+			player: data.player,
+			scene,
+		});
+	}
+
+	pub fn to_ron_string(&self, registry: &AppTypeRegistry) -> ron::Result<String> {
+		ron::ser::to_string_pretty(
+			&SaveFileSerializer {
+				save: self,
+				registry,
+			},
+			ron::ser::PrettyConfig::new().indentor("\t".to_owned()),
+		)
+	}
+
+	pub fn from_ron_str(
+		s: &str,
+		registry: &TypeRegistry,
+	) -> Result<Self, ron::error::SpannedError> {
+		let mut ron_de = ron::de::Deserializer::from_str(s)?;
+		SaveFileDeserializer { registry }
+			.deserialize(&mut ron_de)
+			.map_err(|e| ron_de.span_error(e))
+	}
+}
+
+/// The part of a [`SaveFile`] that has to wait for the player and the active area to spawn.
+#[derive(Resource)]
+pub struct SavedEntities {
+	/// Cleared once the player has been moved here.
+	pub player: Option<Vec3>,
+	pub scene: DynamicScene,
+}
+
+impl SavedEntities {
+	/// Applies the saved components to the [`Resettable`] entities with the same [`Name`].
+	pub fn restore(self, world: &mut World) {
+		let registry = world.resource::<AppTypeRegistry>().clone();
+		let registry = registry.read();
+		let named = world
+			.query_filtered::<(Entity, &Name), With<Resettable>>()
+			.iter(world)
+			.map(|(id, name)| (name.clone(), id))
+			.collect::<HashMap<_, _>>();
+		for saved in self.scene.entities {
+			let Some(name) = saved
+				.components
+				.iter()
+				.find(|component| component.represents::<Name>())
+				.and_then(|component| Name::from_reflect(&**component))
+			else {
+				continue;
+			};
+			let Some(id) = named.get(&name).copied() else {
+				warn!("Saved entity {name} no longer exists");
+				continue;
+			};
+			let mut entity = world.entity_mut(id);
+			for component in &saved.components {
+				let Some(reflect_component) = component
+					.get_represented_type_info()
+					.and_then(|info| registry.get(info.type_id()))
+					.and_then(|reg| reg.data::<ReflectComponent>())
+				else {
+					warn!(
+						"{} is not a registered component",
+						component.reflect_type_path()
+					);
+					continue;
+				};
+				reflect_component.apply_or_insert(&mut entity, &**component, &registry);
+			}
+		}
+	}
+}
+
+/// Puts back the `disabled` flags of a moment's happenings, warning about any that can't be
+/// matched up because the timeline changed since the save.
+fn restore_happenings_flags(
+	moment: &mut Moment,
+	flags: MomentFlags,
+	path: &AssetPath,
+	t: LoopTime,
+) {
+	let unlabelled = moment
+		.happenings
+		.iter()
+		.filter(|h| h.label.is_none())
+		.count();
+	let mut positional = flags.happenings.into_iter();
+	if unlabelled != positional.len() {
+		warn!(
+			"{path} at {t} has {unlabelled} unlabelled happenings, but {} were saved, so their \
+			 `disabled` flags are not restored",
+			positional.len()
+		);
+		positional = Vec::new().into_iter();
+	}
+	for happenings in &mut moment.happenings {
+		let disabled = match happenings.label {
+			Some(label) => flags.labelled.get(&label).copied(),
+			None => positional.next(),
+		};
+		if let Some(disabled) = disabled {
+			happenings.disabled = disabled;
+		}
+	}
+	for label in flags.labelled.keys() {
+		if !moment.happenings.iter().any(|h| h.label == Some(*label)) {
+			warn!("{path} at {t} no longer has happenings labelled {label}");
+		}
+	}
+}
+
+pub struct SaveFileSerializer<'a> {
+	pub save: &'a SaveFile,
+	pub registry: &'a AppTypeRegistry,
+}
+
+impl<'a> Serialize for SaveFileSerializer<'a> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let SaveData {
+			curr,
+			player,
+			timelines,
+			flags,
+			seen_dialogue,
+		} = &self.save.data;
+		let mut state = serializer.serialize_struct("SaveFile", 6)?;
+		state.serialize_field("curr", curr)?;
+		state.serialize_field("player", player)?;
+		state.serialize_field("timelines", timelines)?;
+		state.serialize_field("flags", flags)?;
+		state.serialize_field("seen_dialogue", seen_dialogue)?;
+		state.serialize_field(
+			"world",
+			&SceneSerializer::new(&self.save.world, &self.registry.0),
+		)?;
+		state.end()
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+pub enum SaveFileField {
+	Curr,
+	Player,
+	Timelines,
+	Flags,
+	SeenDialogue,
+	World,
+}
+
+pub struct SaveFileDeserializer<'a> {
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for SaveFileDeserializer<'a> {
+	type Value = SaveFile;
+
+	fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_struct(
+			"SaveFile",
+			&[
+				"curr",
+				"player",
+				"timelines",
+				"flags",
+				"seen_dialogue",
+				"world",
+			],
+			SaveFileVisitor {
+				registry: self.registry,
+			},
+		)
+	}
+}
+
+pub struct SaveFileVisitor<'a> {
+	pub registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> Visitor<'de> for SaveFileVisitor<'a> {
+	type Value = SaveFile;
+
+	fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+		formatter.write_str("struct SaveFile")
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+	where
+		A: MapAccess<'de>,
+	{
+		let mut curr = None;
+		let mut player = None;
+		let mut timelines = None;
+		let mut flags = None;
+		let mut seen_dialogue = None;
+		let mut world = None;
+		while let Some(key) = map.next_key()? {
+			match key {
+				SaveFileField::Curr => curr = Some(map.next_value()?),
+				SaveFileField::Player => player = map.next_value()?,
+				SaveFileField::Timelines => timelines = Some(map.next_value()?),
+				SaveFileField::Flags => flags = Some(map.next_value()?),
+				SaveFileField::SeenDialogue => seen_dialogue = Some(map.next_value()?),
+				SaveFileField::World => {
+					world = Some(map.next_value_seed(SceneDeserializer {
+						type_registry: self.registry,
+					})?)
+				}
+			}
+		}
+		Ok(SaveFile {
+			data: SaveData {
+				curr: curr.ok_or_else(|| Error::missing_field("curr"))?,
+				player,
+				timelines: timelines.unwrap_or_default(),
+				flags: flags.unwrap_or_default(),
+				seen_dialogue: seen_dialogue.unwrap_or_default(),
+			},
+			world: world.unwrap_or_default(),
+		})
+	}
+}
+
+/// Saves the game to the given slot, logging any errors.
+pub fn save_game(world: &mut World, slot: &str) {
+	let Some(save) = SaveFile::capture(world) else {
+		return;
+	};
+	let ron = match save.to_ron_string(world.resource::<AppTypeRegistry>()) {
+		Ok(ron) => ron,
+		Err(e) => {
+			error!("Failed to serialize save file: {e}");
+			return;
+		}
+	};
+	if let Err(e) = storage::write(slot, &ron) {
+		error!("Failed to write save file {slot}: {e}");
+	} else {
+		debug!("Saved game to {slot}");
+	}
+}
+
+/// Loads the game from the given slot, if it exists, logging any errors.
+pub fn load_game(world: &mut World, slot: &str) {
+	let ron = match storage::read(slot) {
+		Ok(Some(ron)) => ron,
+		Ok(None) => return,
+		Err(e) => {
+			error!("Failed to read save file {slot}: {e}");
+			return;
+		}
+	};
+	let registry = world.resource::<AppTypeRegistry>().clone();
+	let save = match SaveFile::from_ron_str(&ron, &registry.read()) {
+		Ok(save) => save,
+		Err(e) => {
+			error!("Failed to parse save file {slot}: {e}");
+			return;
+		}
+	};
+	save.restore(world);
+	info!("Loaded game from {slot}");
+}
+
+pub fn load_autosave(world: &mut World) {
+	load_game(world, AUTOSAVE)
+}
+
+/// Moves the player to where they were saved, as soon as they exist.
+pub fn place_saved_player(
+	mut saved: ResMut<SavedEntities>,
+	mut player: Query<&mut Transform, WithVariant<Root>>,
+) {
+	let Some(pos) = saved.player else {
+		return;
+	};
+	if let Ok(mut xform) = player.get_single_mut() {
+		xform.translation = pos;
+		saved.player = None;
+	}
+}
+
+/// Whether the player is in place and the area they are in has finished spawning, so the saved
+/// entities exist to be restored.
+pub fn active_area_spawned(
+	saved: Res<SavedEntities>,
+	active: Res<ActiveArea>,
+	roots: Query<(&AreaId, &AreaRoot)>,
+) -> bool {
+	let Some(active) = **active else {
+		return false;
+	};
+	saved.player.is_none()
+		&& roots
+			.iter()
+			.any(|(area, root)| area.0 == active && root.tagged)
+}
+
+pub fn restore_saved_entities(world: &mut World) {
+	if let Some(saved) = world.remove_resource::<SavedEntities>() {
+		saved.restore(world);
+	}
+}
+
+pub fn autosave(world: &mut World) {
+	let delta = world.resource::<Time<Real>>().delta();
+	if world.resource_mut::<AutoSave>().tick(delta).just_finished() {
+		save_game(world, AUTOSAVE)
+	}
+}
+
+pub fn save_on_exit(world: &mut World) {
+	let exiting = !world.resource::<Events<AppExit>>().is_empty();
+	if exiting {
+		save_game(world, AUTOSAVE)
+	}
+}
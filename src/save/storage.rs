@@ -0,0 +1,78 @@
+//! Small key-value store for save files and settings.
+//!
+//! Uses local storage on web, and the platform's config directory everywhere else.
+
+pub use platform::{delete, read, write};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+	use directories::ProjectDirs;
+	use std::{
+		fs, io,
+		io::ErrorKind,
+		path::{Path, PathBuf},
+	};
+
+	fn path(name: &str) -> io::Result<PathBuf> {
+		let dirs = ProjectDirs::from("", "", "kairoi")
+			.ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no home directory"))?;
+		Ok(dirs.config_dir().join(format!("{name}.ron")))
+	}
+
+	pub fn read(name: &str) -> io::Result<Option<String>> {
+		match fs::read_to_string(path(name)?) {
+			Ok(s) => Ok(Some(s)),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	pub fn write(name: &str, contents: &str) -> io::Result<()> {
+		let path = path(name)?;
+		if let Some(dir) = path.parent().filter(|dir| !Path::exists(dir)) {
+			fs::create_dir_all(dir)?;
+		}
+		fs::write(path, contents)
+	}
+
+	pub fn delete(name: &str) -> io::Result<()> {
+		match fs::remove_file(path(name)?) {
+			Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+	use std::{io, io::ErrorKind};
+	use web_sys::{wasm_bindgen::JsValue, Storage};
+
+	fn key(name: &str) -> String {
+		format!("kairoi/{name}")
+	}
+
+	fn js_err(e: JsValue) -> io::Error {
+		io::Error::other(format!("{e:?}"))
+	}
+
+	fn storage() -> io::Result<Storage> {
+		web_sys::window()
+			.ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no window"))?
+			.local_storage()
+			.map_err(js_err)?
+			.ok_or_else(|| io::Error::new(ErrorKind::NotFound, "local storage unavailable"))
+	}
+
+	pub fn read(name: &str) -> io::Result<Option<String>> {
+		storage()?.get_item(&key(name)).map_err(js_err)
+	}
+
+	pub fn write(name: &str, contents: &str) -> io::Result<()> {
+		storage()?.set_item(&key(name), contents).map_err(js_err)
+	}
+
+	pub fn delete(name: &str) -> io::Result<()> {
+		storage()?.remove_item(&key(name)).map_err(js_err)
+	}
+}
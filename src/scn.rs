@@ -1,4 +1,4 @@
-use crate::scn::{clock::ClockPlugin, intro::IntroPlugin};
+use crate::scn::{area::AreaPlugin, clock::ClockPlugin, intro::IntroPlugin, portal::PortalPlugin};
 use bevy::{
 	ecs::system::{EntityCommand, EntityCommands},
 	pbr::CascadeShadowConfigBuilder,
@@ -9,8 +9,10 @@ use serde::{Deserialize, Serialize};
 use sond_bevy_enum_components::reflect::AppEnumReflectExt;
 use std::f32::consts::FRAC_PI_6;
 
+pub mod area;
 pub mod clock;
 pub mod intro;
+pub mod portal;
 
 pub struct EnvironmentPlugin;
 
@@ -19,8 +21,10 @@ impl Plugin for EnvironmentPlugin {
 		app.register_variant::<clock::hand::Hour>()
 			.register_variant::<clock::hand::Minute>()
 			.register_type::<Resettable>()
+			.register_type::<ResetPreset>()
 			.add_systems(Startup, setup)
-			.add_plugins((IntroPlugin, ClockPlugin));
+			.add_systems(Update, remember_spawn_transforms)
+			.add_plugins((AreaPlugin, IntroPlugin, ClockPlugin, PortalPlugin));
 	}
 }
 
@@ -55,29 +59,38 @@ pub fn setup(
 	));
 }
 
-#[derive(Component, Reflect, Deref, DerefMut, Serialize, Deserialize)]
+#[derive(Component, Reflect, Default, Serialize, Deserialize)]
 #[reflect(Default, Component, Serialize, Deserialize, no_field_bounds)]
 #[serde(default)]
 pub struct Resettable {
+	/// What resetting does, unless there's a custom `resetter`.
+	pub preset: ResetPreset,
 	#[reflect(ignore)]
 	#[serde(skip)]
-	pub resetter: Box<dyn Resetter>,
+	pub resetter: Option<Box<dyn Resetter>>,
 }
 
 impl Resettable {
 	pub fn new(resetter: impl EntityCommand + Clone + Sync + 'static) -> Self {
 		Self {
-			resetter: Box::new(move |mut cmds: EntityCommands| {
+			preset: default(),
+			resetter: Some(Box::new(move |mut cmds: EntityCommands| {
 				cmds.add(resetter.clone());
-			}),
+			})),
 		}
 	}
-}
 
-impl Default for Resettable {
-	fn default() -> Self {
+	pub fn preset(preset: ResetPreset) -> Self {
 		Self {
-			resetter: default_resetter(),
+			preset,
+			resetter: None,
+		}
+	}
+
+	pub fn defer_reset(&self, cmds: EntityCommands) {
+		match &self.resetter {
+			Some(resetter) => resetter.defer_reset(cmds),
+			None => self.preset.defer_reset(cmds),
 		}
 	}
 }
@@ -92,14 +105,57 @@ impl<F: Fn(EntityCommands) + Send + Sync> Resetter for F {
 	}
 }
 
-pub fn default_resetter() -> Box<dyn Resetter> {
-	Box::new(despawn_self::<true>)
+/// Ways of resetting things that scene files can pick from.
+#[derive(Reflect, Default, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[reflect(Default, Serialize, Deserialize)]
+pub enum ResetPreset {
+	/// Despawns it and its children.
+	#[default]
+	Despawn,
+	/// Moves it back to where it was spawned and rewinds its [`AnimationPlayer`].
+	Rewind,
+	/// Leaves it as it is.
+	Keep,
 }
 
-fn despawn_self<const RECURSIVE: bool>(mut cmds: EntityCommands) {
-	if RECURSIVE {
-		cmds.despawn_recursive();
-	} else {
-		cmds.despawn();
+impl Resetter for ResetPreset {
+	fn defer_reset(&self, mut cmds: EntityCommands) {
+		match self {
+			ResetPreset::Despawn => cmds.despawn_recursive(),
+			ResetPreset::Rewind => {
+				cmds.add(rewind);
+			}
+			ResetPreset::Keep => {}
+		}
+	}
+}
+
+/// Where a [`ResetPreset::Rewind`] entity was spawned.
+#[derive(Component, Copy, Clone, Debug, Deref, DerefMut)]
+pub struct SpawnTransform(pub Transform);
+
+pub fn remember_spawn_transforms(
+	mut cmds: Commands,
+	q: Query<(Entity, &Resettable, &Transform), Added<Resettable>>,
+) {
+	for (id, reset, xform) in &q {
+		if reset.preset == ResetPreset::Rewind {
+			cmds.entity(id).insert(SpawnTransform(*xform));
+		}
+	}
+}
+
+fn rewind(id: Entity, world: &mut World) {
+	let Some(mut entity) = world.get_entity_mut(id) else {
+		return;
+	};
+	if let Some(SpawnTransform(spawned_at)) = entity.get::<SpawnTransform>().copied() {
+		if let Some(mut xform) = entity.get_mut::<Transform>() {
+			*xform = spawned_at;
+		}
+	}
+	if let Some(mut player) = entity.get_mut::<AnimationPlayer>() {
+		player.replay();
+		player.pause();
 	}
 }
@@ -0,0 +1,189 @@
+//! Loads and unloads [`Area`](crate::data::area::Area)s around the player.
+
+use crate::{
+	data::{
+		area::{ActiveArea, AreaId, AreaIndex, Areas},
+		tl::{SpawnedAt, TimeLoop, Timeline},
+		Str,
+	},
+	player::player_entity::Root,
+	GameState,
+};
+use bevy::{
+	asset::UntypedAssetId,
+	prelude::*,
+	scene::SceneInstance,
+	utils::{HashMap, HashSet},
+};
+use sond_bevy_enum_components::WithVariant;
+
+pub struct AreaPlugin;
+
+impl Plugin for AreaPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				stream_areas,
+				tag_area_entities,
+				tag_spawned_in_area,
+				enter_area,
+			)
+				.chain()
+				.run_if(in_state(GameState::Running)),
+		);
+	}
+}
+
+/// The entity a loaded area's scene is spawned under.
+#[derive(Component, Debug, Default)]
+pub struct AreaRoot {
+	/// Whether the scene's entities have been given the area's [`AreaId`] yet.
+	pub tagged: bool,
+}
+
+pub fn stream_areas(
+	mut cmds: Commands,
+	areas: Res<Areas>,
+	indices: Res<Assets<AreaIndex>>,
+	player: Query<&GlobalTransform, WithVariant<Root>>,
+	roots: Query<(Entity, &AreaId), With<AreaRoot>>,
+	owned: Query<(Entity, &AreaId), (Without<AreaRoot>, Without<Parent>)>,
+	srv: Res<AssetServer>,
+	mut active: ResMut<ActiveArea>,
+) {
+	let (Some(index), Ok(player)) = (indices.get(&areas.index), player.get_single()) else {
+		return;
+	};
+	let pos = player.translation().truncate();
+	let loaded = roots
+		.iter()
+		.map(|(id, area)| (area.0, id))
+		.collect::<HashMap<_, _>>();
+	for (name, area) in &index.areas {
+		let dist = area.distance(pos);
+		match loaded.get(name) {
+			None if dist <= index.load_margin => {
+				info!(target: "areas", "loading area {name}");
+				cmds.spawn((
+					DynamicSceneBundle {
+						scene: srv.load(area.scene.clone()),
+						..default()
+					},
+					AreaId(*name),
+					AreaRoot::default(),
+				));
+			}
+			Some(root) if dist > index.unload_margin => {
+				info!(target: "areas", "unloading area {name}");
+				despawn_area(&mut cmds, *name, *root, &owned);
+			}
+			_ => {}
+		}
+		if dist == 0.0 && **active != Some(*name) {
+			**active = Some(*name);
+		}
+	}
+}
+
+/// Despawns an area's scene and everything else tagged with its [`AreaId`].
+pub fn despawn_area(
+	cmds: &mut Commands,
+	area: Str,
+	root: Entity,
+	owned: &Query<(Entity, &AreaId), (Without<AreaRoot>, Without<Parent>)>,
+) {
+	cmds.entity(root).despawn_recursive();
+	for (id, owner) in owned {
+		if owner.0 == area {
+			cmds.entity(id).despawn_recursive();
+		}
+	}
+}
+
+/// Gives everything in an area's scene the area's [`AreaId`] once it has spawned.
+pub fn tag_area_entities(
+	mut cmds: Commands,
+	mut roots: Query<(&AreaId, &SceneInstance, &mut AreaRoot)>,
+	spawner: Res<SceneSpawner>,
+) {
+	for (area, instance, mut root) in &mut roots {
+		if root.tagged || !spawner.instance_is_ready(**instance) {
+			continue;
+		}
+		root.tagged = true;
+		for id in spawner.iter_instance_entities(**instance) {
+			cmds.entity(id).insert(*area);
+		}
+	}
+}
+
+/// Things spawned by happenings belong to the area the player was in at the time.
+pub fn tag_spawned_in_area(
+	mut cmds: Commands,
+	q: Query<Entity, (Added<SpawnedAt>, Without<AreaId>)>,
+	active: Res<ActiveArea>,
+) {
+	let Some(area) = **active else {
+		return;
+	};
+	for id in &q {
+		cmds.entity(id).insert(AreaId(area));
+	}
+}
+
+/// Moves the loop to the active area's timeline when the player walks into a new area.
+pub fn enter_area(
+	active: Res<ActiveArea>,
+	areas: Res<Areas>,
+	indices: Res<Assets<AreaIndex>>,
+	timelines: Res<Assets<Timeline>>,
+	srv: Res<AssetServer>,
+	mut tloop: ResMut<TimeLoop>,
+) {
+	if !active.is_changed() {
+		return;
+	}
+	let Some(name) = **active else {
+		return;
+	};
+	let Some(area) = indices
+		.get(&areas.index)
+		.and_then(|index| index.areas.get(&name))
+	else {
+		return;
+	};
+	let Some(id) = srv
+		.get_path_id(area.timeline.clone())
+		.map(UntypedAssetId::typed)
+	else {
+		error!("Timeline {} for area {name} is not loaded", area.timeline);
+		return;
+	};
+	if branches_from(&timelines, tloop.curr.0, id) {
+		return;
+	}
+	info!(target: "areas", "entered area {name}, moving to {}", area.timeline);
+	tloop.curr.0 = id;
+}
+
+/// Whether `tl` is `ancestor` or branched from it, however indirectly.
+pub fn branches_from(
+	timelines: &Assets<Timeline>,
+	mut tl: AssetId<Timeline>,
+	ancestor: AssetId<Timeline>,
+) -> bool {
+	let mut seen = HashSet::default();
+	loop {
+		if tl == ancestor {
+			return true;
+		}
+		if !seen.insert(tl) {
+			return false;
+		}
+		match timelines.get(tl).and_then(|timeline| timeline.branch_from) {
+			Some(parent) => tl = parent.0,
+			None => return false,
+		}
+	}
+}
@@ -5,6 +5,7 @@ use crate::{
 		LoadAlphaMode, LoadStdMat,
 	},
 	scn::clock::hand::HandItem,
+	time_graph::transition::ActiveTransition,
 	GameState,
 };
 use bevy::{ecs::system::RunSystemOnce, pbr::light_consts::lux::AMBIENT_DAYLIGHT, prelude::*};
@@ -17,7 +18,9 @@ pub struct ClockPlugin;
 
 impl Plugin for ClockPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(Startup, setup.after(crate::cam::setup))
+		app.register_type::<ClockModel>()
+			.add_systems(Startup, setup.after(crate::cam::setup))
+			.add_systems(Update, spawn_clock_models)
 			.add_systems(
 				PostUpdate,
 				fade_clock_on_reset.run_if(in_state(GameState::ResettingLoop)),
@@ -113,6 +116,21 @@ pub fn setup(
 #[derive(Resource)]
 pub struct ClockScene(pub Handle<Scene>);
 
+/// Shows the small [`ClockScene`] on this entity.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct ClockModel;
+
+pub fn spawn_clock_models(
+	mut cmds: Commands,
+	q: Query<Entity, Added<ClockModel>>,
+	clock: Res<ClockScene>,
+) {
+	for id in &q {
+		cmds.entity(id).insert(clock.0.clone());
+	}
+}
+
 impl FromWorld for ClockScene {
 	fn from_world(world: &mut World) -> Self {
 		let mut scn = World::new();
@@ -210,36 +228,29 @@ pub fn tick_hand(mut q: Query<(&mut Transform, Hand)>, loop_time: Res<TimeLoop>)
 pub struct FullscreenClock;
 
 pub fn fade_clock_on_reset(
-	tloop: Res<TimeLoop>,
+	active: Res<ActiveTransition>,
 	q: Query<(&Handle<StandardMaterial>, Option<Hand>), With<FullscreenClock>>,
 	mut lights: Query<&mut DirectionalLight>,
 	mut ambient_light: ResMut<AmbientLight>,
 	mut mats: ResMut<Assets<StandardMaterial>>,
 ) {
-	let TimeLoop {
-		curr,
-		resetting_from: from,
-		resetting_to: to,
-	} = *tloop;
-	let t = if to < from {
-		let range = from - to;
-		1.0 - ((from - curr.1).secs_f32() / range.secs_f32())
-	} else {
-		let range = to - from;
-		(curr.1 - from).secs_f32() / range.secs_f32()
-	};
-	let t = 1.0 - ((t - 0.5).abs() * 2.0);
-	let t = (t * 8.0).clamp(0.0, 1.0);
+	let t = active.effect_strength();
+	let clock_t = if active.transition.fade_clock { t } else { 0.0 };
+	let light_t = if active.transition.dim_lights { t } else { 0.0 };
 	for (handle, hand) in &q {
 		let Some(mat) = mats.get_mut(handle) else {
 			error!("missing material for {handle:?}");
 			continue;
 		};
-		let t = if hand.is_some() { t * 1.2 } else { t };
+		let t = if hand.is_some() {
+			clock_t * 1.2
+		} else {
+			clock_t
+		};
 		mat.base_color = mat.base_color.with_a(t);
 	}
 	for mut light in &mut lights {
-		light.illuminance = AMBIENT_DAYLIGHT * (1.0 - t);
+		light.illuminance = AMBIENT_DAYLIGHT * (1.0 - light_t);
 	}
-	ambient_light.brightness = 80.0 * (1.0 - t);
+	ambient_light.brightness = 80.0 * (1.0 - light_t);
 }
@@ -1,20 +1,19 @@
+//! Custom happenings for the intro room. The room itself is `scn/intro.scn.ron`.
+
 use crate::{
 	data::{
-		cam::AvoidOccludingPlayer,
-		sprites::{LoadSprite3d, Sprite3dBundle},
-		tl::{DoList, ReflectDo, Trigger, TriggerKind},
-		LoadStdMat,
+		anim::Animations,
+		tl::{DoList, ReflectDo, ReflectUndo, Trigger, TriggerKind, Undo},
+		Str,
 	},
 	happens::TakeBranch,
-	scn::{clock::ClockScene, Resettable},
+	scn::Resettable,
 };
-use bevy::{ecs::system::Command, pbr::NotShadowCaster, prelude::*};
+use bevy::{ecs::system::Command, prelude::*};
 use bevy_xpbd_3d::{
 	components::*,
-	parry::shape::SharedShape,
 	prelude::{Collider, Sensor},
 };
-use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::f32::consts::FRAC_PI_2;
 
@@ -24,252 +23,72 @@ impl Plugin for IntroPlugin {
 	fn build(&self, app: &mut App) {
 		app.register_type::<RaiseWalls>()
 			.register_type::<FlipLever>()
-			.add_systems(Startup, setup);
+			.register_type::<OpenPanel>()
+			.register_type::<BreakClock>()
+			.register_type::<Walls>()
+			.register_type::<HackablePanel>()
+			.register_type::<IntroClock>();
 	}
 }
 
-pub fn setup(
-	mut cmds: Commands,
-	srv: Res<AssetServer>,
-	mut meshes: ResMut<Assets<Mesh>>,
-	mut mats: ResMut<Assets<StandardMaterial>>,
-	mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-	mut animations: ResMut<Assets<AnimationClip>>,
-	clock: Res<ClockScene>,
-) {
-	fn panel_trigger() -> impl Bundle {
-		(
-			Trigger {
-				oneshot: true,
-				causes: DoList(vec![Box::new(OpenPanel)]),
-				kind: TriggerKind::Interact {
-					message: "Break into panel".into(),
-				},
-			},
-			TransformBundle::from_transform(Transform {
-				translation: Vec3::new(0.7, 2.0, 0.0),
-				rotation: Quat::from_rotation_z(FRAC_PI_2),
-				..default()
-			}),
-			VisibilityBundle::default(),
-			Sensor,
-			Collider::cuboid(0.8, 0.2, 0.8),
-			Resettable::default(),
-		)
-	}
-	cmds.spawn(panel_trigger());
-
-	let white = mats.add(Color::WHITE);
-	cmds.spawn((
-		PbrBundle {
-			mesh: meshes.add(Cuboid::from_size(Vec3::splat(1.0))),
-			material: white.clone(),
-			transform: Transform::from_translation(Vec3::Y * 2.0),
-			..default()
-		},
-		RigidBody::Static,
-		Collider::cuboid(1.0, 1.0, 1.0),
-	));
-	let Sprite3dBundle { pbr, .. } = LoadSprite3d {
-		size: Vec2::ONE,
-		material: LoadStdMat {
-			base_color_texture: Some("scn/intro/hack_panel.png".into()),
-			double_sided: true,
-			cull_mode: None,
-			..default()
-		},
-		transform: Transform {
-			translation: Vec3::new(0.52, 2.0, 0.0),
-			rotation: Quat::from_rotation_z(FRAC_PI_2),
-			..default()
-		},
-		..default()
-	}
-	.load_using(&srv, &mut meshes, &mut mats, &mut atlas_layouts);
-
-	{
-		fn panel_bundle(pbr: PbrBundle) -> impl Bundle {
-			(
-				pbr.clone(),
-				HackablePanel,
-				LockedAxes::ALL_LOCKED,
-				RigidBody::Dynamic,
-				Collider::round_cuboid(0.8, 0.001, 0.8, 0.05),
-				Restitution::new(0.9),
-				Resettable::new(move |id, world: &mut World| {
-					world.entity_mut(id).despawn();
-					world.spawn(panel_trigger());
-					world.spawn(panel_bundle(pbr));
-				}),
-			)
-		}
-		cmds.spawn(panel_bundle(pbr));
-	}
-
-	cmds.spawn((
-		IntroClock,
-		SceneBundle {
-			scene: clock.0.clone(),
-			transform: Transform {
-				translation: Vec3::new(0.0, 2.0, 1.05),
-				..default()
-			},
-			..default()
-		},
-		RigidBody::Dynamic,
-		LockedAxes::ALL_LOCKED,
-		Collider::cylinder(0.2, 0.5),
-	));
-
-	let panel_mesh = meshes.add(Cuboid::new(12.0, 12.0, 1.0));
-	let panel_col = Collider::cuboid(12.0, 12.0, 1.0);
-	let dark_gray = mats.add(StandardMaterial {
-		base_color: Color::rgb(0.1, 0.1, 0.1),
-		depth_bias: 2.0,
-		..default()
-	});
-
-	cmds.spawn((
-		PbrBundle {
-			mesh: panel_mesh.clone(),
-			material: mats.add(Color::rgb(0.01, 0.01, 0.01)),
-			transform: Transform::from_translation(Vec3::NEG_Z),
-			..default()
-		},
-		RigidBody::Static,
-		panel_col.clone(),
-		NotShadowCaster,
-	));
-
-	cmds.spawn((
-		Walls,
-		Name::new("Walls"),
-		TransformBundle::from_transform(Transform::from_translation(Vec3::Z * -8.0)),
-		VisibilityBundle::default(),
-		Resettable::new(|id, world: &mut World| {
-			let mut entity = world.entity_mut(id);
-			entity
-				.get_mut::<Transform>()
-				.expect("Player definitely has a Transform")
-				.translation
-				.z = -8.0;
-			let mut player = entity
-				.get_mut::<AnimationPlayer>()
-				.expect("Walls should have an AnimationPlayer");
-			player.replay();
-			player.pause();
-		}),
-		AnimationPlayer::default(),
-	))
-	.with_children(|cmds| {
-		cmds.spawn((
-			PbrBundle {
-				mesh: panel_mesh.clone(),
-				material: dark_gray.clone(),
-				transform: Transform {
-					translation: Vec3::new(0.0, 6.5, 0.0),
-					rotation: Quat::from_rotation_x(FRAC_PI_2),
-					..default()
-				},
-				..default()
-			},
-			RigidBody::Static,
-			panel_col.clone(),
-			NotShadowCaster,
-			AvoidOccludingPlayer {
-				area_shape: RwLock::new(Some(SharedShape::cuboid(6.0, 6.0, 2.5))),
-				area_transform: Transform::from_translation(Vec3::NEG_Z * 2.5),
-				..default()
-			},
-		));
-		cmds.spawn((
-			PbrBundle {
-				mesh: panel_mesh.clone(),
-				material: dark_gray.clone(),
-				transform: Transform {
-					translation: Vec3::new(-5.5, 0.0, 0.0),
-					rotation: Quat::from_rotation_y(FRAC_PI_2),
-					..default()
-				},
-				..default()
-			},
-			RigidBody::Static,
-			panel_col.clone(),
-			NotShadowCaster,
-		));
-		cmds.spawn((
-			PbrBundle {
-				mesh: panel_mesh.clone(),
-				material: dark_gray.clone(),
-				transform: Transform {
-					translation: Vec3::new(5.5, 0.0, 0.0),
-					rotation: Quat::from_rotation_y(FRAC_PI_2),
-					..default()
-				},
-				..default()
-			},
-			RigidBody::Static,
-			panel_col.clone(),
-			NotShadowCaster,
-		));
-		cmds.spawn((
-			PbrBundle {
-				mesh: panel_mesh.clone(),
-				material: dark_gray.clone(),
-				transform: Transform {
-					translation: Vec3::new(0.0, -6.5, 0.0),
-					rotation: Quat::from_rotation_x(FRAC_PI_2),
-					..default()
-				},
-				visibility: Visibility::Hidden,
-				..default()
-			},
-			RigidBody::Static,
-			panel_col.clone(),
-			NotShadowCaster,
-			AvoidOccludingPlayer {
-				area_shape: RwLock::new(Some(SharedShape::cuboid(6.0, 6.0, 2.5))),
-				area_transform: Transform::from_translation(Vec3::NEG_Z * 2.5),
-				..default()
-			},
-		));
-	});
-
-	let mut clip = AnimationClip::default();
-	clip.add_curve_to_path(
-		EntityPath {
-			parts: vec!["Walls".into()],
-		},
-		VariableCurve {
-			keyframe_timestamps: vec![0.0, 0.5],
-			keyframes: Keyframes::Translation(vec![Vec3::Z * -8.0, Vec3::Z * -4.0]),
-			interpolation: Interpolation::Linear,
-		},
-	);
-	let raise_walls = animations.add(clip);
-	cmds.insert_resource(RaiseWallsClip(raise_walls));
-}
-
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct Walls;
 
+/// Name of the walls' rising animation in their [`Animations`].
+pub const RAISE_WALLS: &str = "raise";
+
 #[derive(Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
-#[reflect(Do, Serialize, Deserialize)]
+#[reflect(Do, Undo, Serialize, Deserialize)]
 pub struct RaiseWalls;
 
-#[derive(Resource, Default, Debug, Deref, DerefMut)]
-pub struct RaiseWallsClip(pub Handle<AnimationClip>);
+fn raise_walls_clip(world: &mut World) -> Option<(Entity, Handle<AnimationClip>)> {
+	let mut q = world.query_filtered::<(Entity, &Animations), With<Walls>>();
+	let Ok((id, animations)) = q.get_single(world) else {
+		error!("Failed to find the intro's Walls");
+		return None;
+	};
+	let Some(clip) = animations.get(&Str::from(RAISE_WALLS)) else {
+		error!("Walls have no {RAISE_WALLS:?} animation");
+		return None;
+	};
+	Some((id, clip.clone()))
+}
 
 impl Command for RaiseWalls {
 	fn apply(self, world: &mut World) {
-		let clip = world.resource::<RaiseWallsClip>().0.clone();
-		let mut q = world.query_filtered::<&mut AnimationPlayer, With<Walls>>();
-		q.single_mut(world).play(clip).resume();
+		let Some((id, clip)) = raise_walls_clip(world) else {
+			return;
+		};
+		if let Some(mut player) = world.get_mut::<AnimationPlayer>(id) {
+			player.play(clip).resume();
+		}
+	}
+}
+
+impl Undo for RaiseWalls {
+	fn undo(&self, world: &mut World) {
+		let Some((id, clip)) = raise_walls_clip(world) else {
+			return;
+		};
+		let duration = world
+			.resource::<Assets<AnimationClip>>()
+			.get(&clip)
+			.map_or(0.0, AnimationClip::duration);
+		let Some(mut player) = world.get_mut::<AnimationPlayer>(id) else {
+			return;
+		};
+		if player.is_playing_clip(&clip) && !player.is_paused() && !player.is_finished() {
+			// Lower from wherever they've been raised to so far
+			player.set_speed(-1.0);
+		} else {
+			player.start(clip).seek_to(duration).set_speed(-1.0);
+		}
 	}
 }
 
 #[derive(Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
-#[reflect(Do, Serialize, Deserialize)]
+#[reflect(Do, Undo, Serialize, Deserialize)]
 pub struct FlipLever;
 
 impl Command for FlipLever {
@@ -286,11 +105,18 @@ impl Command for FlipLever {
 	}
 }
 
+impl Undo for FlipLever {
+	fn undo(&self, world: &mut World) {
+		Command::apply(FlipLever, world)
+	}
+}
+
 #[derive(Copy, Clone, Default, Debug, Reflect, Serialize, Deserialize)]
 #[reflect(Do, Serialize, Deserialize)]
 pub struct OpenPanel;
 
-#[derive(Component, Debug)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct HackablePanel;
 
 impl Command for OpenPanel {
@@ -312,6 +138,7 @@ impl Command for OpenPanel {
 				kind: TriggerKind::Interact {
 					message: "Hack Detonator".into(),
 				},
+				..default()
 			},
 			TransformBundle::from_transform(Transform {
 				translation: Vec3::new(0.7, 2.0, 0.0),
@@ -330,7 +157,8 @@ impl Command for OpenPanel {
 #[reflect(Do, Serialize, Deserialize)]
 pub struct BreakClock;
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
 pub struct IntroClock;
 
 impl Command for BreakClock {
@@ -0,0 +1,123 @@
+use crate::{data::tl::PortalTo, time_graph::TookPortal, GameState};
+use bevy::prelude::*;
+use std::{f32::consts::TAU, time::Duration};
+
+/// How long the screen takes to fade back in after taking a portal.
+pub const PORTAL_FADE: Duration = Duration::from_millis(400);
+pub const PORTAL_COLOR: Color = Color::rgba(0.55, 0.8, 1.0, 0.6);
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				add_portal_visuals,
+				spin_portals.run_if(not(in_state(GameState::Paused))),
+				start_portal_fade,
+				fade_portal_transition,
+			),
+		);
+	}
+
+	fn finish(&self, app: &mut App) {
+		app.init_resource::<PortalAssets>();
+	}
+}
+
+#[derive(Resource)]
+pub struct PortalAssets {
+	pub mesh: Handle<Mesh>,
+	pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PortalAssets {
+	fn from_world(world: &mut World) -> Self {
+		let mesh = world
+			.resource_mut::<Assets<Mesh>>()
+			.add(Torus::new(0.4, 0.5));
+		let material = world
+			.resource_mut::<Assets<StandardMaterial>>()
+			.add(StandardMaterial {
+				base_color: PORTAL_COLOR,
+				emissive: PORTAL_COLOR * 4.0,
+				alpha_mode: AlphaMode::Blend,
+				unlit: true,
+				..default()
+			});
+		Self { mesh, material }
+	}
+}
+
+/// The ring shown around a [`PortalTo`].
+#[derive(Component, Debug)]
+pub struct PortalVisual;
+
+/// Full-screen flash shown briefly after taking a portal.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct PortalFade(pub Timer);
+
+pub fn add_portal_visuals(
+	mut cmds: Commands,
+	q: Query<Entity, Added<PortalTo>>,
+	assets: Res<PortalAssets>,
+) {
+	for id in &q {
+		cmds.entity(id)
+			.insert(VisibilityBundle::default())
+			.with_children(|cmds| {
+				cmds.spawn((
+					PbrBundle {
+						mesh: assets.mesh.clone(),
+						material: assets.material.clone(),
+						..default()
+					},
+					PortalVisual,
+				));
+			});
+	}
+}
+
+pub fn spin_portals(mut q: Query<&mut Transform, With<PortalVisual>>, t: Res<Time>) {
+	let pulse = 1.0 + (t.elapsed_seconds() * TAU * 0.5).sin() * 0.05;
+	for mut xform in &mut q {
+		xform.rotate_local_y(t.delta_seconds() * TAU * 0.25);
+		xform.scale = Vec3::splat(pulse);
+	}
+}
+
+pub fn start_portal_fade(mut cmds: Commands, mut events: EventReader<TookPortal>) {
+	if events.read().last().is_none() {
+		return;
+	}
+	cmds.spawn((
+		NodeBundle {
+			style: Style {
+				position_type: PositionType::Absolute,
+				width: Val::Percent(100.0),
+				height: Val::Percent(100.0),
+				..default()
+			},
+			background_color: PORTAL_COLOR.with_a(1.0).into(),
+			z_index: ZIndex::Global(i32::MAX),
+			..default()
+		},
+		PortalFade(Timer::new(PORTAL_FADE, TimerMode::Once)),
+	));
+}
+
+pub fn fade_portal_transition(
+	mut cmds: Commands,
+	mut q: Query<(Entity, &mut PortalFade, &mut BackgroundColor)>,
+	t: Res<Time>,
+) {
+	for (id, mut fade, mut color) in &mut q {
+		fade.tick(t.delta());
+		if fade.finished() {
+			cmds.entity(id).despawn_recursive();
+		} else {
+			color.0.set_a(1.0 - fade.fraction());
+		}
+	}
+}
@@ -0,0 +1,304 @@
+//! Headless harness for exercising the time graph without a window or renderer.
+
+use crate::{
+	data::{
+		anim::LoadAnimations,
+		area::{AreaDataPlugin, Areas},
+		cam::AvoidOccludingPlayer,
+		pbr::LoadMesh,
+		phys::{ColliderShape, LoadBody},
+		sprites::LoadSprite3d,
+		tl::{LoadedTimelines, LoopTime, TPath, TimeLoop, Timeline, Timelines, T},
+		LoadAsset,
+	},
+	player::player_entity::Root,
+	scn::{
+		area::AreaPlugin,
+		clock::ClockModel,
+		intro::{HackablePanel, IntroClock, Walls},
+		Resettable,
+	},
+	time_graph::{transition::start_transition, AppliedHappening, HappeningsLog, TimeGraphPlugin},
+	GameState, HeadlessDataPlugin, TYPE_REGISTRY,
+};
+use bevy::{
+	asset::{AssetMetaCheck, AssetPath, LoadState},
+	ecs::system::RunSystemOnce,
+	pbr::NotShadowCaster,
+	prelude::*,
+	render::view::{InheritedVisibility, ViewVisibility},
+	scene::ScenePlugin,
+	time::TimeUpdateStrategy,
+};
+use sond_bevy_enum_components::EntityEnumCommands;
+use std::time::Duration;
+
+/// How many frames to wait for timelines to load before giving up.
+pub const MAX_LOAD_FRAMES: usize = 10_000;
+
+/// Builds a [`TimeGraphHarness`].
+pub struct HeadlessAppBuilder {
+	pub asset_dir: String,
+	pub timelines: Vec<AssetPath<'static>>,
+	pub start: Option<TPath>,
+	pub step: Duration,
+	pub dry_run: bool,
+	pub areas: bool,
+}
+
+impl Default for HeadlessAppBuilder {
+	fn default() -> Self {
+		Self {
+			asset_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/assets").to_owned(),
+			timelines: Vec::new(),
+			start: None,
+			step: Duration::from_millis(100),
+			dry_run: true,
+			areas: false,
+		}
+	}
+}
+
+impl HeadlessAppBuilder {
+	/// Load an extra timeline in addition to the ones the game always loads.
+	pub fn with_timeline(mut self, path: impl Into<AssetPath<'static>>) -> Self {
+		self.timelines.push(path.into());
+		self
+	}
+
+	/// Start the loop in a timeline other than the default one.
+	pub fn start_at(mut self, start: TPath) -> Self {
+		self.start = Some(start);
+		self
+	}
+
+	/// Amount of loop time each [`TimeGraphHarness::update`] advances by.
+	pub fn step(mut self, step: Duration) -> Self {
+		self.step = step;
+		self
+	}
+
+	/// Whether happenings are only recorded (`true`, the default), or also applied to the world.
+	pub fn dry_run(mut self, dry_run: bool) -> Self {
+		self.dry_run = dry_run;
+		self
+	}
+
+	/// Stream in the shipped areas' scenes around a stand-in player at the origin.
+	///
+	/// Only the scenes' types are registered, so nothing in them gets a mesh or a body.
+	pub fn with_areas(mut self) -> Self {
+		self.areas = true;
+		self
+	}
+
+	pub fn build(self) -> TimeGraphHarness {
+		let mut app = App::new();
+		app.insert_resource(AssetMetaCheck::Never)
+			.add_plugins((
+				MinimalPlugins,
+				AssetPlugin {
+					file_path: self.asset_dir,
+					mode: AssetMode::Unprocessed,
+					..default()
+				},
+				HierarchyPlugin,
+				TransformPlugin,
+			))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(self.step))
+			.insert_resource(HappeningsLog {
+				entries: Vec::new(),
+				dry_run: self.dry_run,
+			})
+			.insert_state(GameState::Loading);
+
+		// Other tests in the same process may have already set this, but every harness registers
+		// the same types, so any of them will do. The asset server is per app, so harnesses never
+		// set `ASSET_SERVER`; anything that needs one is passed it explicitly.
+		let _ = TYPE_REGISTRY.set(app.world.resource::<AppTypeRegistry>().0.clone());
+
+		app.add_plugins((HeadlessDataPlugin, TimeGraphPlugin));
+		if self.areas {
+			add_areas(&mut app);
+		}
+
+		app.finish();
+		app.cleanup();
+
+		let mut harness = TimeGraphHarness { app };
+		for path in self.timelines {
+			harness.load_timeline(path);
+		}
+		harness.wait_for_timelines();
+		if let Some(start) = self.start {
+			harness.jump_to(start);
+		}
+		harness
+			.app
+			.world
+			.resource_mut::<NextState<GameState>>()
+			.set(GameState::Running);
+		// Apply the state transition without advancing the loop.
+		harness.app.world.run_schedule(StateTransition);
+		harness
+	}
+}
+
+fn add_areas(app: &mut App) {
+	app.add_plugins((ScenePlugin, AreaDataPlugin, AreaPlugin))
+		.register_type::<Visibility>()
+		.register_type::<InheritedVisibility>()
+		.register_type::<ViewVisibility>()
+		.register_type::<NotShadowCaster>()
+		.register_type::<LoadAsset<StandardMaterial>>()
+		.register_type::<LoadMesh>()
+		.register_type::<LoadAnimations>()
+		.register_type::<LoadSprite3d>()
+		.register_type::<AvoidOccludingPlayer>()
+		.register_type::<ColliderShape>()
+		.register_type::<LoadBody>()
+		.register_type::<Resettable>()
+		.register_type::<Walls>()
+		.register_type::<HackablePanel>()
+		.register_type::<IntroClock>()
+		.register_type::<ClockModel>();
+	let index = app.world.resource::<AssetServer>().load("world.areas.ron");
+	app.insert_resource(Areas { index });
+	app.world.run_system_once(|mut cmds: Commands| {
+		cmds.spawn(TransformBundle::default()).with_enum(Root);
+	});
+}
+
+/// A headless [`App`] that only runs the time graph, with a manually driven clock.
+pub struct TimeGraphHarness {
+	pub app: App,
+}
+
+impl TimeGraphHarness {
+	pub fn builder() -> HeadlessAppBuilder {
+		HeadlessAppBuilder::default()
+	}
+
+	pub fn load_timeline(&mut self, path: AssetPath<'static>) -> Handle<Timeline> {
+		let handle = self
+			.app
+			.world
+			.resource::<AssetServer>()
+			.load::<Timeline>(path.clone());
+		self.app
+			.world
+			.resource_mut::<LoadedTimelines>()
+			.insert(path, handle.clone());
+		handle
+	}
+
+	/// Updates the app until the [`TimelineIndex`](crate::data::tl::TimelineIndex) and every
+	/// [`LoadedTimelines`] entry is loaded.
+	///
+	/// # Panics
+	/// If any timeline fails to load, or loading takes more than [`MAX_LOAD_FRAMES`].
+	pub fn wait_for_timelines(&mut self) {
+		for _ in 0..MAX_LOAD_FRAMES {
+			let srv = self.app.world.resource::<AssetServer>();
+			let index = &self.app.world.resource::<Timelines>().index;
+			let mut all_loaded = match srv.load_state(index) {
+				LoadState::Failed => panic!("failed to load the timeline index"),
+				_ => srv.is_loaded_with_dependencies(index),
+			};
+			for (path, handle) in self.app.world.resource::<LoadedTimelines>().iter() {
+				match srv.load_state(handle) {
+					LoadState::Loaded => {}
+					LoadState::Failed => panic!("failed to load timeline {path}"),
+					_ => all_loaded = false,
+				}
+			}
+			if all_loaded {
+				return;
+			}
+			self.app.update();
+		}
+		panic!("timelines did not load within {MAX_LOAD_FRAMES} frames");
+	}
+
+	pub fn timeline_id(&self, path: impl Into<AssetPath<'static>>) -> AssetId<Timeline> {
+		let path = path.into();
+		self.app
+			.world
+			.resource::<AssetServer>()
+			.get_path_id(path.clone())
+			.unwrap_or_else(|| panic!("timeline {path} is not loaded"))
+			.typed()
+	}
+
+	pub fn jump_to(&mut self, to: TPath) {
+		let id = self.timeline_id(to.0);
+		self.app.world.resource_mut::<TimeLoop>().curr = T(id, to.1);
+	}
+
+	pub fn now(&self) -> LoopTime {
+		self.app.world.resource::<TimeLoop>().curr.1
+	}
+
+	/// Runs a single frame.
+	pub fn update(&mut self) {
+		self.app.update();
+	}
+
+	/// Runs frames until the loop reaches `t`.
+	///
+	/// # Panics
+	/// If the loop hasn't reached `t` after `max_frames`.
+	pub fn run_until(&mut self, t: LoopTime, max_frames: usize) {
+		for _ in 0..max_frames {
+			if self.now() >= t {
+				return;
+			}
+			self.app.update();
+		}
+		if self.now() < t {
+			panic!(
+				"loop only reached {} of {t} within {max_frames} frames",
+				self.now()
+			);
+		}
+	}
+
+	/// Rewinds the loop to `to` the same way [`ResetLoop`](crate::happens::ResetLoop) does, and
+	/// runs frames until it's done.
+	///
+	/// # Panics
+	/// If it isn't done after `max_frames`.
+	pub fn rewind_to(&mut self, to: LoopTime, max_frames: usize) {
+		start_transition(&mut self.app.world, to, None);
+		for _ in 0..max_frames {
+			self.app.update();
+			if *self.app.world.resource::<State<GameState>>() == GameState::Running {
+				return;
+			}
+		}
+		panic!(
+			"rewinding to {to} only reached {} within {max_frames} frames",
+			self.now()
+		);
+	}
+
+	pub fn applied(&self) -> &[AppliedHappening] {
+		&self.app.world.resource::<HappeningsLog>().entries
+	}
+
+	/// Type paths of all applied happenings, in order.
+	pub fn applied_type_paths(&self) -> Vec<&str> {
+		self.applied()
+			.iter()
+			.map(|applied| applied.action.reflect_type_path())
+			.collect()
+	}
+
+	pub fn clear_applied(&mut self) {
+		self.app
+			.world
+			.resource_mut::<HappeningsLog>()
+			.entries
+			.clear();
+	}
+}
@@ -1,34 +1,80 @@
 use crate::{
 	data::{
-		tl::{Lifetime, LoopTime, PortalTo, SpawnedAt, TimeLoop, Timeline, Trigger, TriggerKind},
+		dlg::ActiveDialogue,
+		tl::{
+			Activator, AssetServerExt, Condition, Do, Happenings, Lifetime, LoadPortal,
+			LoadedTimelines, LoopDelta, LoopTime, Moment, PortalTo, SpawnedAt, TimeDilationZone,
+			TimeLoop, TimeScale, Timeline, Trigger, TriggerKind, T,
+		},
 		ui::{InteractSign, InteractText},
 		Str,
 	},
 	happens::reset_world,
-	player::{player_entity::Root, Action},
+	player::{player_entity::Root, Action, Facing},
 	GameState,
 };
-use bevy::{prelude::*, utils::intern::Interned};
-use bevy_xpbd_3d::prelude::CollidingEntities;
+use analysis::TimeGraph;
+use bevy::{
+	ecs::system::{Command, CommandQueue},
+	prelude::*,
+	utils::{intern::Interned, HashSet},
+};
+use bevy_xpbd_3d::prelude::{CollidingEntities, Physics, PhysicsTime, RigidBody, Sensor};
 use leafwing_input_manager::prelude::ActionState;
+use reload::{hot_reload_timelines, TimelineReloadPolicy, TimelineSnapshots};
 use sond_bevy_enum_components::WithVariant;
-use std::{cmp::Ordering, f32::consts::TAU, ops::Range};
+use std::{
+	fmt::{Debug, Formatter},
+	ops::Range,
+};
+use transition::{ActiveTransition, LoopTransition, LoopTransitions};
+
+pub mod analysis;
+pub mod reload;
+pub mod transition;
 
 pub struct TimeGraphPlugin;
 
 impl Plugin for TimeGraphPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(First, handle_lifetimes)
+		app.add_event::<TookPortal>()
+			.init_resource::<TimelineSnapshots>()
+			.init_resource::<TimelineReloadPolicy>()
+			.init_resource::<LoopTransitions>()
+			.init_resource::<ActiveTransition>()
+			.init_resource::<InteractFocus>()
+			.register_type::<TimelineReloadPolicy>()
+			.register_type::<LoopTransition>()
+			.register_type::<LoopTransitions>()
+			.add_systems(
+				First,
+				handle_lifetimes.run_if(not(in_state(GameState::Paused))),
+			)
 			.add_systems(
 				PreUpdate,
-				(step_loop, take_portal).run_if(in_state(GameState::Running)),
+				(
+					(dilation_zones, apply_time_scale).chain(),
+					(step_loop, take_portal).run_if(in_state(GameState::Running)),
+				)
+					.chain(),
+			)
+			.add_systems(
+				Update,
+				(
+					resolve_portals,
+					insert_zone_sensors,
+					insert_trigger_state,
+					seek.run_if(in_state(GameState::ResettingLoop)),
+				),
 			)
-			.add_systems(Update, seek.run_if(in_state(GameState::ResettingLoop)))
 			.add_systems(
 				PostUpdate,
 				(
 					print_timelines,
-					check_triggers.run_if(in_state(GameState::Running)),
+					validate_time_graph,
+					(update_interact_focus, check_triggers, check_sensor_triggers)
+						.chain()
+						.run_if(in_state(GameState::Running)),
 				),
 			);
 	}
@@ -39,10 +85,11 @@ pub fn step_loop(
 	mut tloop: ResMut<TimeLoop>,
 	timelines: Res<Assets<Timeline>>,
 	asrv: Res<AssetServer>,
-	t: Res<Time>,
+	delta: Res<LoopDelta>,
+	log: Option<ResMut<HappeningsLog>>,
 ) {
 	let prev = tloop.curr.1;
-	tloop.curr.1 += t.delta();
+	tloop.curr.1 += **delta;
 	let id = tloop.curr.0;
 	handle_happenings(
 		cmds,
@@ -54,16 +101,50 @@ pub fn step_loop(
 		// events when resetting the loop to exactly `prev`.
 		prev..tloop.curr.1,
 		id,
+		log.map(ResMut::into_inner),
 	)
 }
 
+/// Record of every [`Do`](crate::data::tl::Do) that [`handle_happenings`] has applied.
+///
+/// Only tracked if the resource exists, which is mainly useful for tests.
+#[derive(Resource, Default, Debug)]
+pub struct HappeningsLog {
+	pub entries: Vec<AppliedHappening>,
+	/// Record happenings without actually applying them.
+	pub dry_run: bool,
+}
+
+pub struct AppliedHappening {
+	pub timeline: AssetId<Timeline>,
+	pub at: LoopTime,
+	pub label: Option<Str>,
+	pub action: Box<dyn Do>,
+	/// Whether this was [`Do::undo`]ne by [`undo_happenings`] rather than applied.
+	pub undone: bool,
+}
+
+impl Debug for AppliedHappening {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("AppliedHappening")
+			.field("timeline", &self.timeline)
+			.field("at", &self.at)
+			.field("label", &self.label)
+			.field("action", &self.action.as_reflect())
+			.field("undone", &self.undone)
+			.finish()
+	}
+}
+
 pub fn handle_happenings(
 	mut cmds: Commands,
 	asrv: &AssetServer,
 	timelines: &Assets<Timeline>,
 	range: Range<LoopTime>,
 	tl: AssetId<Timeline>,
+	mut log: Option<&mut HappeningsLog>,
 ) {
+	let id = tl;
 	let path = asrv
 		.get_path(tl)
 		.map_or_else(String::new, |path| format!("{path}: "));
@@ -84,6 +165,7 @@ pub fn handle_happenings(
 				timelines,
 				range.start..end,
 				branch_from.0,
+				log.as_deref_mut(),
 			);
 		}
 		if range.contains(&branch_from.1) {
@@ -91,22 +173,7 @@ pub fn handle_happenings(
 		}
 	}
 	for (lt, mom) in tl.moments.range(range.clone()) {
-		if mom.disabled {
-			debug!(target: "time_graph", "[disabled] {}@{lt}", mom.label.unwrap_or(Str(Interned(""))));
-			continue;
-		}
-		debug!(target: "time_graph", desc = mom.desc.as_deref(), "{path}{}@{lt}", mom.label.unwrap_or(Str(Interned(""))));
-		for (i, happenings) in mom.happenings.iter().enumerate() {
-			if happenings.disabled {
-				debug!(target: "time_graph", "\t└ [disabled] {}", happenings.label.unwrap_or_else(|| (&*format!("{i}")).into()));
-				continue;
-			} else {
-				debug!(target: "time_graph", "\t└ {}", happenings.label.unwrap_or_else(|| (&*format!("{i}")).into()));
-			}
-			for happen in &happenings.actions {
-				happen.apply(cmds.reborrow());
-			}
-		}
+		handle_moment(cmds.reborrow(), &path, id, *lt, mom, log.as_deref_mut());
 	}
 	if let Some(merge_into) = tl.merge_into.as_ref() {
 		if range.contains(&merge_into.1) {
@@ -118,7 +185,181 @@ pub fn handle_happenings(
 			} else {
 				merge_into.1
 			};
-			handle_happenings(cmds, asrv, timelines, start..range.end, merge_into.0);
+			handle_happenings(cmds, asrv, timelines, start..range.end, merge_into.0, log);
+		}
+	}
+}
+
+/// Applies (or, for a dry run, only logs) the happenings of a single moment of timeline `id`.
+///
+/// `path` is only used as a prefix for log messages.
+pub fn handle_moment(
+	mut cmds: Commands,
+	path: &str,
+	id: AssetId<Timeline>,
+	lt: LoopTime,
+	mom: &Moment,
+	mut log: Option<&mut HappeningsLog>,
+) {
+	if mom.disabled {
+		debug!(target: "time_graph", "[disabled] {}@{lt}", mom.label.unwrap_or(Str(Interned(""))));
+		return;
+	}
+	debug!(target: "time_graph", desc = mom.desc.as_deref(), "{path}{}@{lt}", mom.label.unwrap_or(Str(Interned(""))));
+	for (i, happenings) in mom.happenings.iter().enumerate() {
+		if happenings.disabled {
+			debug!(target: "time_graph", "\t└ [disabled] {}", happenings.label.unwrap_or_else(|| (&*format!("{i}")).into()));
+			continue;
+		} else {
+			debug!(target: "time_graph", "\t└ {}", happenings.label.unwrap_or_else(|| (&*format!("{i}")).into()));
+		}
+		if happenings.is_conditional() {
+			// Conditions are checked against the world once earlier happenings have been applied.
+			cmds.add(ConditionalHappenings {
+				timeline: id,
+				at: lt,
+				label: happenings.label,
+				actions: happenings.actions.iter().map(|a| a.clone_do()).collect(),
+				when: happenings
+					.when
+					.iter()
+					.map(|c| c.clone_condition())
+					.collect(),
+				unless: happenings
+					.unless
+					.iter()
+					.map(|c| c.clone_condition())
+					.collect(),
+			});
+			continue;
+		}
+		for happen in &happenings.actions {
+			if let Some(log) = log.as_deref_mut() {
+				log.entries.push(AppliedHappening {
+					timeline: id,
+					at: lt,
+					label: happenings.label,
+					action: happen.clone_do(),
+					undone: false,
+				});
+				if log.dry_run {
+					continue;
+				}
+			}
+			happen.apply(cmds.reborrow());
+		}
+	}
+}
+
+/// A [`Happenings`] group that only happens if its conditions hold once the command is applied.
+pub struct ConditionalHappenings {
+	pub timeline: AssetId<Timeline>,
+	pub at: LoopTime,
+	pub label: Option<Str>,
+	pub actions: Vec<Box<dyn Do>>,
+	pub when: Vec<Box<dyn Condition>>,
+	pub unless: Vec<Box<dyn Condition>>,
+}
+
+impl Command for ConditionalHappenings {
+	fn apply(self, world: &mut World) {
+		if !Happenings::conditions_hold(&self.when, &self.unless, world) {
+			debug!(target: "time_graph", "\t└ [conditions not met] {}", self.label.unwrap_or(Str(Interned(""))));
+			return;
+		}
+		if let Some(mut log) = world.get_resource_mut::<HappeningsLog>() {
+			for happen in &self.actions {
+				log.entries.push(AppliedHappening {
+					timeline: self.timeline,
+					at: self.at,
+					label: self.label,
+					action: happen.clone_do(),
+					undone: false,
+				});
+			}
+			if log.dry_run {
+				return;
+			}
+		}
+		let mut queue = CommandQueue::default();
+		let mut cmds = Commands::new(&mut queue, world);
+		for happen in &self.actions {
+			happen.apply(cmds.reborrow());
+		}
+		queue.apply(world);
+	}
+}
+
+/// Plays [`handle_happenings`] backwards while the loop rewinds, undoing
+/// every reversible happening in `range` from latest to earliest.
+pub fn undo_happenings(
+	mut cmds: Commands,
+	asrv: &AssetServer,
+	timelines: &Assets<Timeline>,
+	range: Range<LoopTime>,
+	tl: AssetId<Timeline>,
+	mut log: Option<&mut HappeningsLog>,
+) {
+	let id = tl;
+	let path = asrv
+		.get_path(tl)
+		.map_or_else(String::new, |path| format!("{path}: "));
+	let Some(tl) = timelines.get(tl) else {
+		error!("timeline {path} should exist");
+		return;
+	};
+	if let Some(merge_into) = tl.merge_into.as_ref() {
+		if range.end > merge_into.1 {
+			undo_happenings(
+				cmds.reborrow(),
+				asrv,
+				timelines,
+				range.start.max(merge_into.1)..range.end,
+				merge_into.0,
+				log.as_deref_mut(),
+			);
+		}
+	}
+	for (lt, mom) in tl.moments.range(range.clone()).rev() {
+		if mom.disabled {
+			continue;
+		}
+		debug!(target: "time_graph", "[undo] {path}{}@{lt}", mom.label.unwrap_or(Str(Interned(""))));
+		for happenings in mom.happenings.iter().rev() {
+			// No way to know whether conditional happenings actually happened.
+			if happenings.disabled || happenings.is_conditional() {
+				continue;
+			}
+			for happen in happenings.actions.iter().rev() {
+				let undone = match log.as_deref_mut() {
+					Some(log) if log.dry_run => happen.reversible(),
+					_ => happen.undo(cmds.reborrow()),
+				};
+				if !undone {
+					continue;
+				}
+				if let Some(log) = log.as_deref_mut() {
+					log.entries.push(AppliedHappening {
+						timeline: id,
+						at: *lt,
+						label: happenings.label,
+						action: happen.clone_do(),
+						undone: true,
+					});
+				}
+			}
+		}
+	}
+	if let Some(branch_from) = tl.branch_from.as_ref() {
+		if range.start < branch_from.1 {
+			undo_happenings(
+				cmds,
+				asrv,
+				timelines,
+				range.start..range.end.min(branch_from.1),
+				branch_from.0,
+				log,
+			);
 		}
 	}
 }
@@ -139,18 +380,224 @@ pub fn print_timelines(mut events: EventReader<AssetEvent<Timeline>>, assets: Re
 	}
 }
 
+/// Logs any problems with the time graph whenever a timeline (re)loads.
+pub fn validate_time_graph(
+	mut events: EventReader<AssetEvent<Timeline>>,
+	timelines: Res<Assets<Timeline>>,
+	transitions: Res<LoopTransitions>,
+	srv: Res<AssetServer>,
+) {
+	let changed = events.read().any(|ev| {
+		matches!(
+			ev,
+			AssetEvent::LoadedWithDependencies { .. } | AssetEvent::Modified { .. }
+		)
+	});
+	if !changed {
+		return;
+	}
+	let graph = TimeGraph::new(&timelines, &srv);
+	for e in graph.validate() {
+		error!(target: "time_graph", "{e}");
+	}
+	for (id, tl) in timelines.iter() {
+		for (t, name) in transitions.unknown_in(tl) {
+			let path = srv.get_path(id).map(|path| path.to_string());
+			let path = path.as_deref().unwrap_or("");
+			error!(target: "time_graph", "{path}: unknown loop transition {name} at {t}");
+		}
+	}
+}
+
+/// Sent when the player goes through a [`PortalTo`].
+#[derive(Event, Debug, Copy, Clone)]
+pub struct TookPortal {
+	pub portal: Entity,
+	pub to: T,
+}
+
 pub fn take_portal(
-	player: Query<&CollidingEntities, WithVariant<Root>>,
-	portals: Query<&PortalTo>,
+	mut player: Query<(&CollidingEntities, &mut Transform), WithVariant<Root>>,
+	portals: Query<(&PortalTo, &GlobalTransform)>,
 	mut tl: ResMut<TimeLoop>,
+	mut took: EventWriter<TookPortal>,
+	// Portals the player was already inside last frame, so standing in one doesn't keep
+	// sending them back to its destination.
+	mut inside: Local<HashSet<Entity>>,
 ) {
-	let Ok(colliding) = player.get_single() else {
+	let Ok((colliding, mut xform)) = player.get_single_mut() else {
 		return;
 	};
+	let was_inside = std::mem::take(&mut *inside);
 	for id in colliding.iter().copied() {
-		if let Ok(portal) = portals.get(id) {
-			tl.curr = portal.0;
+		let Ok((portal, portal_xform)) = portals.get(id) else {
+			continue;
+		};
+		inside.insert(id);
+		if was_inside.contains(&id) {
+			continue;
+		}
+		tl.curr = portal.portal.to;
+		if let Some(exit) = portal.exit {
+			let offset = if portal.carry_offset {
+				xform.translation - portal_xform.translation()
+			} else {
+				Vec3::ZERO
+			};
+			xform.translation = exit + offset;
 		}
+		took.send(TookPortal {
+			portal: id,
+			to: portal.portal.to,
+		});
+	}
+}
+
+pub fn insert_zone_sensors(mut cmds: Commands, q: Query<Entity, Added<TimeDilationZone>>) {
+	for id in &q {
+		cmds.entity(id).insert(Sensor);
+	}
+}
+
+/// Updates [`TimeScale::zones`] from the [`TimeDilationZone`]s the player is in.
+pub fn dilation_zones(
+	player: Query<&CollidingEntities, WithVariant<Root>>,
+	zones: Query<&TimeDilationZone>,
+	mut scale: ResMut<TimeScale>,
+) {
+	let zones_scale = player.get_single().map_or(1.0, |colliding| {
+		colliding
+			.iter()
+			.filter_map(|id| zones.get(*id).ok())
+			.map(|zone| zone.scale)
+			.product()
+	});
+	if scale.zones != zones_scale {
+		scale.zones = zones_scale;
+	}
+}
+
+/// Applies the [`TimeScale`] to the [`LoopDelta`], physics and animations. Leaves
+/// `Time<Virtual>` alone so UI and input keep their pace.
+pub fn apply_time_scale(
+	scale: Res<TimeScale>,
+	state: Res<State<GameState>>,
+	t: Res<Time>,
+	mut delta: ResMut<LoopDelta>,
+	physics: Option<ResMut<Time<Physics>>>,
+	mut anims: Query<&mut AnimationPlayer>,
+) {
+	// Rewinding runs at its own pace.
+	let speed = if *state.get() == GameState::ResettingLoop {
+		1.0
+	} else {
+		scale.get()
+	};
+	delta.0 = t.delta().mul_f32(speed);
+	if let Some(mut physics) = physics {
+		if physics.relative_speed() != speed {
+			physics.set_relative_speed(speed);
+		}
+	}
+	for mut anim in &mut anims {
+		// Keeps the direction, since undoing some happenings plays their animation backwards.
+		let want = anim.speed().signum() * speed;
+		if anim.speed() != want {
+			anim.set_speed(want);
+		}
+	}
+}
+
+pub fn resolve_portals(
+	mut cmds: Commands,
+	q: Query<(Entity, &LoadPortal), Changed<LoadPortal>>,
+	srv: Res<AssetServer>,
+	mut loaded: ResMut<LoadedTimelines>,
+) {
+	for (id, load) in &q {
+		for path in [&load.path.from.0, &load.path.to.0] {
+			if !loaded.contains_key(path) {
+				loaded.insert(path.clone(), srv.load(path.clone()));
+			}
+		}
+		let Some(portal) = srv.portal_for_portal_path(load.path.clone()) else {
+			error!("Failed to resolve portal {:?}", load.path);
+			continue;
+		};
+		cmds.entity(id).insert(PortalTo {
+			portal,
+			exit: load.exit,
+			carry_offset: load.carry_offset,
+		});
+	}
+}
+
+/// The [`TriggerKind::Interact`] trigger that [`Action::Interact`] fires, out of the ones the
+/// player is touching.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InteractFocus {
+	pub focused: Option<Entity>,
+	/// Every interactable trigger the player is touching, best first.
+	pub candidates: Vec<Entity>,
+	/// Whether the player cycled to `focused`, in which case it stays focused while it is in reach.
+	pub picked: bool,
+}
+
+/// How good a candidate for [`InteractFocus`] a trigger at `target` is. Lower is better.
+///
+/// Nearer is better, and triggers behind the player count as up to 3 times as far away as ones
+/// straight ahead.
+pub fn interact_score(pos: Vec2, facing: Vec2, target: Vec2) -> f32 {
+	let to = target - pos;
+	to.length() * (2.0 - to.normalize_or_zero().dot(facing))
+}
+
+pub fn update_interact_focus(
+	player: Query<
+		(
+			&CollidingEntities,
+			&ActionState<Action>,
+			&GlobalTransform,
+			&Facing,
+		),
+		WithVariant<Root>,
+	>,
+	triggers: Query<(&Trigger, &GlobalTransform)>,
+	mut focus: ResMut<InteractFocus>,
+) {
+	let Ok((colliding, inputs, xform, facing)) = player.get_single() else {
+		return;
+	};
+	let pos = xform.translation().truncate();
+	let mut candidates = colliding
+		.iter()
+		.filter_map(|id| {
+			let (trigger, xform) = triggers.get(*id).ok()?;
+			let TriggerKind::Interact { .. } = trigger.kind else {
+				return None;
+			};
+			Some((
+				*id,
+				interact_score(pos, **facing, xform.translation().truncate()),
+			))
+		})
+		.collect::<Vec<_>>();
+	candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+	let candidates = candidates.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+	let curr = focus
+		.focused
+		.and_then(|id| candidates.iter().position(|it| *it == id));
+	if inputs.just_pressed(&Action::CycleInteract) && !candidates.is_empty() {
+		let next = curr.map_or(0, |i| (i + 1) % candidates.len());
+		focus.focused = Some(candidates[next]);
+		focus.picked = true;
+	} else if curr.is_none() || !focus.picked {
+		focus.focused = candidates.first().copied();
+		focus.picked = false;
+	}
+	if focus.candidates != candidates {
+		focus.candidates = candidates;
 	}
 }
 
@@ -160,6 +607,8 @@ pub fn check_triggers(
 	triggers: Query<&Trigger>,
 	mut interact_sign: Query<&mut Visibility, With<InteractSign>>,
 	mut interact_text: Query<&mut Text, With<InteractText>>,
+	focus: Res<InteractFocus>,
+	dialogue: Option<Res<ActiveDialogue>>,
 ) {
 	let Ok((colliding, inputs)) = player.get_single() else {
 		return;
@@ -169,33 +618,129 @@ pub fn check_triggers(
 	let mut text = interact_text.single_mut();
 	let mut interact_msg = None;
 	for id in colliding.iter().copied() {
-		if let Ok(trigger) = triggers.get(id) {
-			if let TriggerKind::Interact { message } = trigger.kind {
-				interact_msg = Some(message);
-				if !inputs.just_pressed(&Action::Interact) {
-					continue;
-				}
-			}
-			for to_do in trigger.causes.iter() {
-				to_do.apply(cmds.reborrow());
-			}
-			if trigger.oneshot {
-				cmds.entity(id).despawn();
-			}
+		let Ok(trigger) = triggers.get(id) else {
+			continue;
+		};
+		let TriggerKind::Interact { message } = trigger.kind else {
+			continue;
+		};
+		// Interact advances the dialogue instead while it is open.
+		if dialogue.is_some() || focus.focused != Some(id) {
+			continue;
+		}
+		interact_msg = Some(message);
+		if inputs.just_pressed(&Action::Interact) {
+			fire_trigger(cmds.reborrow(), id, trigger);
 		}
 	}
 	if let Some(msg) = interact_msg {
 		if *vis != Visibility::Visible {
 			*vis = Visibility::Visible;
 		}
-		if *text.sections[0].value != **msg {
-			text.sections[0].value = msg.to_string();
+		let msg = match focus.candidates.len() {
+			0 | 1 => msg.to_string(),
+			n => {
+				let i = focus
+					.focused
+					.and_then(|id| focus.candidates.iter().position(|it| *it == id))
+					.unwrap_or(0);
+				format!("{msg} ({}/{n})", i + 1)
+			}
+		};
+		if text.sections[0].value != msg {
+			text.sections[0].value = msg;
 		}
 	} else if *vis == Visibility::Visible {
 		*vis = Visibility::Hidden
 	}
 }
 
+pub fn fire_trigger(mut cmds: Commands, id: Entity, trigger: &Trigger) {
+	for to_do in trigger.causes.iter() {
+		to_do.apply(cmds.reborrow());
+	}
+	if trigger.oneshot {
+		cmds.entity(id).despawn();
+	}
+}
+
+/// What [`check_sensor_triggers`] remembers about a [`Trigger`] between frames.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TriggerState {
+	/// Whether its [`Activator`] was touching it last frame.
+	pub occupied: bool,
+	/// When it last became occupied.
+	pub since: LoopTime,
+	/// When it last fired since becoming occupied.
+	pub fired_at: Option<LoopTime>,
+}
+
+/// Lets triggers see what is touching them, rather than only the player seeing triggers.
+pub fn insert_trigger_state(mut cmds: Commands, q: Query<Entity, Added<Trigger>>) {
+	for id in &q {
+		cmds.entity(id)
+			.insert((CollidingEntities::default(), TriggerState::default()));
+	}
+}
+
+/// Fires every trigger other than [`TriggerKind::Interact`] as its [`TriggerKind`] says.
+pub fn check_sensor_triggers(
+	mut cmds: Commands,
+	mut triggers: Query<(Entity, &Trigger, &CollidingEntities, &mut TriggerState)>,
+	player: Query<(), WithVariant<Root>>,
+	others: Query<(Option<&Name>, Option<&Visibility>, Option<&RigidBody>)>,
+	tloop: Res<TimeLoop>,
+) {
+	let now = tloop.curr.1;
+	for (id, trigger, colliding, mut state) in &mut triggers {
+		if let TriggerKind::Interact { .. } = trigger.kind {
+			continue;
+		}
+		let occupied = colliding.iter().any(|other| {
+			let Ok((name, vis, body)) = others.get(*other) else {
+				return false;
+			};
+			// Hidden things, like echoes waiting for their loop to catch up, don't count.
+			if vis == Some(&Visibility::Hidden) {
+				return false;
+			}
+			match trigger.by {
+				Activator::Player => player.contains(*other),
+				Activator::Named(want) => name.map_or(false, |name| name.as_str() == &**want),
+				// Otherwise the floor and walls it overlaps would keep it occupied.
+				Activator::Any => body.map_or(false, |body| !body.is_static()),
+			}
+		});
+		let entered = occupied && !state.occupied;
+		let exited = !occupied && state.occupied;
+		if entered {
+			state.since = now;
+			state.fired_at = None;
+		}
+		state.occupied = occupied;
+
+		let fire = match trigger.kind {
+			TriggerKind::Enter => entered,
+			TriggerKind::Exit => exited,
+			TriggerKind::Stay { after } => {
+				occupied && state.fired_at.is_none() && now - state.since >= after
+			}
+			// Also fire if the loop went back past the last time it fired.
+			TriggerKind::Cooldown { period } => {
+				occupied
+					&& state
+						.fired_at
+						.map_or(true, |at| now < at || now - at >= period)
+			}
+			TriggerKind::Interact { .. } => false,
+		};
+		if fire {
+			state.fired_at = Some(now);
+			fire_trigger(cmds.reborrow(), id, trigger);
+		}
+	}
+}
+
 pub fn handle_lifetimes(
 	mut cmds: Commands,
 	q: Query<(Entity, &SpawnedAt, &Lifetime)>,
@@ -211,51 +756,47 @@ pub fn handle_lifetimes(
 pub fn seek(
 	mut cmds: Commands,
 	mut tloop: ResMut<TimeLoop>,
+	mut active: ResMut<ActiveTransition>,
 	mut next_state: ResMut<NextState<GameState>>,
 	t: Res<Time>,
+	timelines: Res<Assets<Timeline>>,
+	asrv: Res<AssetServer>,
+	log: Option<ResMut<HappeningsLog>>,
 ) {
-	let dt = t.delta_seconds();
 	let TimeLoop {
 		ref mut curr,
 		resetting_from: from,
 		resetting_to: to,
 	} = *tloop;
 	let prev = curr.1;
-	match to.cmp(&from) {
-		Ordering::Less => {
-			let from_s = from.secs_f32();
-			let to_s = to.secs_f32();
-			let range = from_s - to_s;
-			let t = 1.0 - ((from_s - prev.secs_f32()) / range);
-			let speed = ((t - 0.5) * TAU).cos() * 0.5 + 0.5;
-			let dt = dt + (dt * speed * 8.0);
-			curr.1 -= LoopTime::from(dt);
-			if t < 0.4 && t + (dt / range) >= 0.4 {
-				cmds.add(reset_world);
-			}
-			if curr.1 <= to {
-				curr.1 = to;
-				next_state.set(GameState::Running);
-			}
-		}
-		Ordering::Greater => {
-			let from_s = from.secs_f32();
-			let to_s = to.secs_f32();
-			let range = to_s - from_s;
-			let t = (prev.secs_f32() - from_s) / range;
-			let speed = ((t - 0.5) * TAU).cos() * 0.5 + 0.5;
-			let dt = dt + (dt * speed * 8.0);
-			curr.1 += LoopTime::from(dt);
-			if t < 0.4 && t + (dt / range) >= 0.4 {
-				cmds.add(reset_world);
-			}
-			if curr.1 >= to {
-				curr.1 = to;
-				next_state.set(GameState::Running);
-			}
-		}
-		Ordering::Equal => {
-			warn!("to == from");
-		}
+	active.elapsed += t.delta();
+	let progress = active.progress();
+	let done = progress >= 1.0;
+	curr.1 = if done {
+		to
+	} else {
+		let eased = active.transition.easing.ease(progress);
+		from + LoopTime::from((to - from).secs_f32() * eased)
+	};
+	let reset_now = !active.reset_done && (done || progress >= active.transition.reset_at);
+	if to < from && !active.reset_done {
+		// Whatever is left is undone along with the reset, before `reset_world` reloads the
+		// timelines it's undone against.
+		let range = if reset_now { to..prev } else { curr.1..prev };
+		undo_happenings(
+			cmds.reborrow(),
+			&asrv,
+			&timelines,
+			range,
+			curr.0,
+			log.map(ResMut::into_inner),
+		);
+	}
+	if reset_now {
+		active.reset_done = true;
+		cmds.add(reset_world);
+	}
+	if done {
+		next_state.set(GameState::Running);
 	}
 }
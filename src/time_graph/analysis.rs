@@ -0,0 +1,301 @@
+//! Static analysis of the graph formed by timelines' `branch_from` and `merge_into` links.
+
+use crate::data::tl::{LoopTime, TPath, Timeline, TimelineLink, T};
+use bevy::{
+	asset::AssetPath,
+	prelude::*,
+	utils::{HashMap, HashSet},
+};
+use std::{
+	error::Error,
+	fmt::{self, Display, Formatter},
+};
+
+/// Problems with the time graph that would make `handle_happenings` or
+/// [`TakeBranch`](crate::happens::TakeBranch) misbehave.
+#[derive(Clone, Debug)]
+pub enum GraphError {
+	/// A link points to a timeline that isn't loaded.
+	Dangling {
+		timeline: Node,
+		link: TimelineLink,
+		target: Node,
+	},
+	/// A link's path was never loaded, so it couldn't be resolved to an `AssetId` at all.
+	UnresolvedPath {
+		timeline: Node,
+		link: TimelineLink,
+		path: TPath,
+	},
+	/// Following links from `cycle[0]` eventually leads back to it.
+	Cycle { cycle: Vec<Node> },
+	/// The timeline merges back into its target before it has even branched off.
+	MergeBeforeBranch {
+		timeline: Node,
+		branch_at: LoopTime,
+		merge_at: LoopTime,
+	},
+	/// No chain of branches and merges leads from `from` to `to`.
+	Unreachable { from: Node, to: Node },
+}
+
+impl Display for GraphError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			GraphError::Dangling {
+				timeline,
+				link,
+				target,
+			} => write!(
+				f,
+				"{timeline}: `{link}` points to missing timeline {target}"
+			),
+			GraphError::UnresolvedPath {
+				timeline,
+				link,
+				path,
+			} => write!(
+				f,
+				"{timeline}: `{link}` timeline {} was never loaded",
+				path.0
+			),
+			GraphError::Cycle { cycle } => {
+				f.write_str("timelines form a cycle: ")?;
+				for node in cycle {
+					write!(f, "{node} -> ")?;
+				}
+				match cycle.first() {
+					Some(first) => write!(f, "{first}"),
+					None => Ok(()),
+				}
+			}
+			GraphError::MergeBeforeBranch {
+				timeline,
+				branch_at,
+				merge_at,
+			} => write!(
+				f,
+				"{timeline}: merges at {merge_at}, before it branches at {branch_at}"
+			),
+			GraphError::Unreachable { from, to } => {
+				write!(f, "{to} can't be reached from {from}")
+			}
+		}
+	}
+}
+
+impl Error for GraphError {}
+
+/// A timeline as it appears in a [`GraphError`], printed as its path when it has one.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Node {
+	pub id: AssetId<Timeline>,
+	pub path: Option<AssetPath<'static>>,
+}
+
+impl Display for Node {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match &self.path {
+			Some(path) => write!(f, "{path}"),
+			None => write!(f, "{}", self.id),
+		}
+	}
+}
+
+/// Snapshot of the links between all loaded timelines.
+#[derive(Clone, Debug, Default)]
+pub struct TimeGraph {
+	pub nodes: HashMap<AssetId<Timeline>, Links>,
+	pub paths: HashMap<AssetId<Timeline>, AssetPath<'static>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Links {
+	pub branch_from: Option<T>,
+	pub merge_into: Option<T>,
+	pub unresolved: Vec<(TimelineLink, TPath)>,
+}
+
+impl Links {
+	pub fn iter(&self) -> impl Iterator<Item = (TimelineLink, T)> {
+		self.branch_from
+			.map(|t| (TimelineLink::BranchFrom, t))
+			.into_iter()
+			.chain(self.merge_into.map(|t| (TimelineLink::MergeInto, t)))
+	}
+}
+
+impl TimeGraph {
+	pub fn new(timelines: &Assets<Timeline>, srv: &AssetServer) -> Self {
+		let mut nodes = HashMap::new();
+		let mut paths = HashMap::new();
+		for (id, tl) in timelines.iter() {
+			nodes.insert(
+				id,
+				Links {
+					branch_from: tl.branch_from,
+					merge_into: tl.merge_into,
+					unresolved: tl.unresolved.clone(),
+				},
+			);
+			if let Some(path) = srv.get_path(id) {
+				paths.insert(id, path.into_owned());
+			}
+		}
+		Self { nodes, paths }
+	}
+
+	/// `id` along with its path, for error messages.
+	pub fn node(&self, id: AssetId<Timeline>) -> Node {
+		Node {
+			id,
+			path: self.paths.get(&id).cloned(),
+		}
+	}
+
+	/// All problems found in the graph, not including reachability.
+	pub fn validate(&self) -> Vec<GraphError> {
+		let mut errors = Vec::new();
+		for (id, links) in &self.nodes {
+			errors.extend(self.link_errors(*id, links));
+		}
+		errors.extend(self.cycles());
+		errors
+	}
+
+	/// Like [`Self::validate`], but also reports every timeline that can't be reached from the
+	/// start of `from`.
+	pub fn validate_from(&self, from: AssetId<Timeline>) -> Vec<GraphError> {
+		let mut errors = self.validate();
+		let reachable = self.reachable(T(from, LoopTime::default()));
+		for id in self.nodes.keys() {
+			if !reachable.contains_key(id) {
+				errors.push(GraphError::Unreachable {
+					from: self.node(from),
+					to: self.node(*id),
+				});
+			}
+		}
+		errors
+	}
+
+	fn link_errors(&self, id: AssetId<Timeline>, links: &Links) -> Vec<GraphError> {
+		let mut errors = Vec::new();
+		for (link, t) in links.iter() {
+			if !self.nodes.contains_key(&t.0) {
+				errors.push(GraphError::Dangling {
+					timeline: self.node(id),
+					link,
+					target: self.node(t.0),
+				});
+			}
+		}
+		for (link, path) in &links.unresolved {
+			errors.push(GraphError::UnresolvedPath {
+				timeline: self.node(id),
+				link: *link,
+				path: path.clone(),
+			});
+		}
+		if let (Some(branch), Some(merge)) = (links.branch_from, links.merge_into) {
+			if merge.1 < branch.1 {
+				errors.push(GraphError::MergeBeforeBranch {
+					timeline: self.node(id),
+					branch_at: branch.1,
+					merge_at: merge.1,
+				});
+			}
+		}
+		errors
+	}
+
+	/// Every cycle reachable by following links forward, each reported once.
+	pub fn cycles(&self) -> Vec<GraphError> {
+		let mut errors = Vec::new();
+		let mut done = HashSet::new();
+		for start in self.nodes.keys() {
+			let mut stack = Vec::new();
+			self.find_cycles(*start, &mut stack, &mut done, &mut errors);
+		}
+		errors
+	}
+
+	fn find_cycles(
+		&self,
+		id: AssetId<Timeline>,
+		stack: &mut Vec<AssetId<Timeline>>,
+		done: &mut HashSet<AssetId<Timeline>>,
+		errors: &mut Vec<GraphError>,
+	) {
+		if done.contains(&id) {
+			return;
+		}
+		if let Some(i) = stack.iter().position(|on_stack| *on_stack == id) {
+			errors.push(GraphError::Cycle {
+				cycle: stack[i..].iter().map(|id| self.node(*id)).collect(),
+			});
+			return;
+		}
+		let Some(links) = self.nodes.get(&id) else {
+			return;
+		};
+		stack.push(id);
+		for (_, t) in links.iter() {
+			self.find_cycles(t.0, stack, done, errors);
+		}
+		stack.pop();
+		done.insert(id);
+	}
+
+	/// Every timeline the loop can get to from `from`, with the earliest time it can arrive.
+	///
+	/// A branch can be taken if it splits off at or after the time its parent is reached, and a
+	/// timeline can only merge back into its `merge_into` target, never into its parent.
+	pub fn reachable(&self, from: T) -> HashMap<AssetId<Timeline>, LoopTime> {
+		let mut earliest = HashMap::new();
+		let mut queue = vec![from];
+		while let Some(T(id, at)) = queue.pop() {
+			if earliest.get(&id).is_some_and(|prev| *prev <= at) {
+				continue;
+			}
+			earliest.insert(id, at);
+			for (child, links) in &self.nodes {
+				if let Some(branch) = links.branch_from {
+					if branch.0 == id && branch.1 >= at {
+						queue.push(T(*child, branch.1));
+					}
+				}
+			}
+			if let Some(merge) = self.nodes.get(&id).and_then(|links| links.merge_into) {
+				queue.push(merge);
+			}
+		}
+		earliest
+	}
+
+	/// Checks whether jumping from the current timeline and time to `to` is valid.
+	pub fn check_branch(&self, from: T, to: AssetId<Timeline>) -> Result<(), GraphError> {
+		let unreachable = || GraphError::Unreachable {
+			from: self.node(from.0),
+			to: self.node(to),
+		};
+		let Some(links) = self.nodes.get(&to) else {
+			return Err(unreachable());
+		};
+		if let Some(e) = self.link_errors(to, links).into_iter().next() {
+			return Err(e);
+		}
+		if let Some(e) = self.cycles().into_iter().find(|e| match e {
+			GraphError::Cycle { cycle } => {
+				cycle.iter().any(|node| node.id == to || node.id == from.0)
+			}
+			_ => false,
+		}) {
+			return Err(e);
+		}
+		if !self.reachable(from).contains_key(&to) {
+			return Err(unreachable());
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,258 @@
+//! Picking up edits to timeline files without restarting the loop.
+
+use super::{handle_moment, HappeningsLog};
+use crate::{
+	data::{
+		tl::{LoopTime, TimeLoop, Timeline},
+		Str,
+	},
+	GameState,
+};
+use bevy::{
+	prelude::*,
+	utils::{HashMap, HashSet},
+};
+use std::collections::BTreeMap;
+
+/// How [`hot_reload_timelines`] treats a timeline that changed on disk.
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct TimelineReloadPolicy {
+	/// Put back `disabled` flags that [`ModifyTimeline`](crate::happens::ModifyTimeline) changed
+	/// before the reload, wherever the moment and happenings labels still match.
+	pub keep_edits: bool,
+	/// Apply moments that were added before the current [`LoopTime`], since the loop has already
+	/// gone past them.
+	pub rerun_added: bool,
+}
+
+impl Default for TimelineReloadPolicy {
+	fn default() -> Self {
+		Self {
+			keep_edits: true,
+			rerun_added: false,
+		}
+	}
+}
+
+/// What [`hot_reload_timelines`] needs to remember about each timeline between reloads.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct TimelineSnapshots(pub HashMap<AssetId<Timeline>, TimelineSnapshot>);
+
+#[derive(Default, Debug)]
+pub struct TimelineSnapshot {
+	/// Labels of the timeline's moments as of the last time it changed.
+	pub moments: BTreeMap<LoopTime, Option<Str>>,
+	/// `disabled` flags set at runtime, by moment label and then happenings label.
+	pub edits: HashMap<(Str, Option<Str>), bool>,
+}
+
+impl TimelineSnapshots {
+	/// Remembers a runtime change to a `disabled` flag. Unlabelled moments can't be matched up
+	/// after a reload, so their edits are not kept.
+	pub fn record_edit(
+		&mut self,
+		id: AssetId<Timeline>,
+		moment: Option<Str>,
+		happenings: Option<Str>,
+		disabled: bool,
+	) {
+		let Some(moment) = moment else {
+			return;
+		};
+		self.entry(id)
+			.or_default()
+			.edits
+			.insert((moment, happenings), disabled);
+	}
+
+	/// Forgets every runtime edit, e.g. because the timelines are being reloaded to undo them.
+	pub fn clear_edits(&mut self) {
+		for snapshot in self.values_mut() {
+			snapshot.edits.clear();
+		}
+	}
+}
+
+pub fn moment_labels(tl: &Timeline) -> BTreeMap<LoopTime, Option<Str>> {
+	tl.moments.iter().map(|(t, mom)| (*t, mom.label)).collect()
+}
+
+/// Diffs timelines against their [`TimelineSnapshots`] whenever they are modified, and applies the
+/// [`TimelineReloadPolicy`].
+pub fn hot_reload_timelines(
+	mut cmds: Commands,
+	mut events: EventReader<AssetEvent<Timeline>>,
+	mut timelines: ResMut<Assets<Timeline>>,
+	mut snapshots: ResMut<TimelineSnapshots>,
+	policy: Res<TimelineReloadPolicy>,
+	tloop: Res<TimeLoop>,
+	state: Res<State<GameState>>,
+	asrv: Res<AssetServer>,
+	mut log: Option<ResMut<HappeningsLog>>,
+) {
+	for ev in events.read() {
+		let id = match ev {
+			AssetEvent::Added { id } | AssetEvent::LoadedWithDependencies { id } => {
+				if let Some(tl) = timelines.get(*id) {
+					snapshots.entry(*id).or_insert_with(|| TimelineSnapshot {
+						moments: moment_labels(tl),
+						edits: default(),
+					});
+				}
+				continue;
+			}
+			AssetEvent::Removed { id } => {
+				snapshots.remove(id);
+				continue;
+			}
+			AssetEvent::Modified { id } => *id,
+			AssetEvent::Unused { .. } => continue,
+		};
+		let Some(tl) = timelines.get(id) else {
+			continue;
+		};
+		let moments = moment_labels(tl);
+		let Some(snapshot) = snapshots.get_mut(&id) else {
+			snapshots.insert(
+				id,
+				TimelineSnapshot {
+					moments,
+					edits: default(),
+				},
+			);
+			continue;
+		};
+
+		// A labelled moment that only moved has been around before, so it doesn't count as new.
+		let old_labels = snapshot
+			.moments
+			.values()
+			.flatten()
+			.copied()
+			.collect::<HashSet<_>>();
+		let added = moments
+			.iter()
+			.filter(|(t, label)| {
+				!snapshot.moments.contains_key(t)
+					&& label.map_or(true, |label| !old_labels.contains(&label))
+			})
+			.map(|(t, _)| *t)
+			.collect::<Vec<_>>();
+		snapshot.moments = moments;
+
+		let path = asrv
+			.get_path(id)
+			.map_or_else(String::new, |path| format!("{path}: "));
+		if !added.is_empty() {
+			info!(target: "time_graph", "{path}added moments at {added:?}");
+		}
+
+		if policy.keep_edits {
+			let lost = lost_edits(tl, &snapshot.edits);
+			// Only touch the asset if something actually needs restoring, since that sends
+			// another `Modified` event.
+			if !lost.is_empty() {
+				if let Some(tl) = timelines.get_mut(id) {
+					for (t, happenings, disabled) in lost {
+						let Some(mom) = tl.moments.get_mut(&t) else {
+							continue;
+						};
+						match happenings {
+							None => mom.disabled = disabled,
+							Some(i) => mom.happenings[i].disabled = disabled,
+						}
+					}
+				}
+			}
+		}
+
+		if !policy.rerun_added || *state.get() != GameState::Running {
+			continue;
+		}
+		let Some(tl) = timelines.get(id) else {
+			continue;
+		};
+		for t in added {
+			if t >= tloop.curr.1 || !happens_in(&timelines, tloop.curr.0, id, t) {
+				continue;
+			}
+			if let Some(mom) = tl.moments.get(&t) {
+				info!(target: "time_graph", "{path}running moment added at {t}");
+				handle_moment(cmds.reborrow(), &path, id, t, mom, log.as_deref_mut());
+			}
+		}
+	}
+}
+
+/// Runtime edits that the reloaded timeline no longer reflects, as the moment, the index of the
+/// happenings (or `None` for the moment itself), and the `disabled` flag to restore.
+fn lost_edits(
+	tl: &Timeline,
+	edits: &HashMap<(Str, Option<Str>), bool>,
+) -> Vec<(LoopTime, Option<usize>, bool)> {
+	let mut lost = Vec::new();
+	for ((moment, happenings), disabled) in edits {
+		let Some((t, mom)) = tl
+			.moments
+			.iter()
+			.find(|(_, mom)| mom.label == Some(*moment))
+		else {
+			continue;
+		};
+		match happenings {
+			None => {
+				if mom.disabled != *disabled {
+					lost.push((*t, None, *disabled));
+				}
+			}
+			Some(label) => {
+				let Some(i) = mom
+					.happenings
+					.iter()
+					.position(|it| it.label == Some(*label))
+				else {
+					continue;
+				};
+				if mom.happenings[i].disabled != *disabled {
+					lost.push((*t, Some(i), *disabled));
+				}
+			}
+		}
+	}
+	lost
+}
+
+/// Whether [`handle_happenings`](super::handle_happenings) for timeline `tl` would handle the
+/// moment at `t` in timeline `id`.
+fn happens_in(
+	timelines: &Assets<Timeline>,
+	tl: AssetId<Timeline>,
+	id: AssetId<Timeline>,
+	t: LoopTime,
+) -> bool {
+	let mut seen = HashSet::default();
+	let mut queue = vec![tl];
+	while let Some(tl) = queue.pop() {
+		if tl == id {
+			return true;
+		}
+		if !seen.insert(tl) {
+			continue;
+		}
+		let Some(timeline) = timelines.get(tl) else {
+			continue;
+		};
+		if let Some(branch_from) = timeline.branch_from {
+			if t < branch_from.1 {
+				queue.push(branch_from.0);
+			}
+		}
+		if let Some(merge_into) = timeline.merge_into {
+			if t >= merge_into.1 {
+				queue.push(merge_into.0);
+			}
+		}
+	}
+	false
+}
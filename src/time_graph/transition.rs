@@ -0,0 +1,207 @@
+//! How the loop gets from one time to another when it is reset.
+
+use crate::{
+	data::{
+		tl::{LoopTime, TimeLoop, Timeline},
+		Str,
+	},
+	happens::{ResetLoop, SpawnTrigger},
+	GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use std::{f32::consts::PI, time::Duration};
+
+/// Name of the transition used when [`ResetLoop`](crate::happens::ResetLoop) doesn't pick one.
+pub const DEFAULT_TRANSITION: &str = "rewind";
+
+#[derive(Clone, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoopTransition {
+	/// Real time the whole transition takes, however far it has to seek.
+	#[serde(with = "crate::data::duration_str")]
+	pub duration: Duration,
+	pub easing: Easing,
+	/// Fraction of the way through at which the world is reset, ideally while the screen is
+	/// obscured.
+	pub reset_at: f32,
+	/// Fade in the full-screen clock towards the middle of the transition.
+	pub fade_clock: bool,
+	/// Dim the lights towards the middle of the transition.
+	pub dim_lights: bool,
+}
+
+impl Default for LoopTransition {
+	fn default() -> Self {
+		Self {
+			duration: Duration::from_secs(3),
+			easing: Easing::Smooth,
+			reset_at: 0.4,
+			fade_clock: true,
+			dim_lights: true,
+		}
+	}
+}
+
+impl LoopTransition {
+	pub fn instant() -> Self {
+		Self {
+			duration: Duration::ZERO,
+			fade_clock: false,
+			dim_lights: false,
+			..default()
+		}
+	}
+
+	pub fn slow_rewind() -> Self {
+		Self {
+			duration: Duration::from_secs(8),
+			..default()
+		}
+	}
+
+	pub fn glitch() -> Self {
+		Self {
+			duration: Duration::from_millis(1500),
+			easing: Easing::Steps(6),
+			fade_clock: false,
+			..default()
+		}
+	}
+
+	/// Progress through the transition after `elapsed`, from 0 to 1.
+	pub fn progress(&self, elapsed: Duration) -> f32 {
+		if self.duration.is_zero() {
+			1.0
+		} else {
+			(elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+		}
+	}
+
+	/// How strongly visual effects should show at `progress`: 0 at either end, fully on around
+	/// `reset_at` so they hide the reset.
+	pub fn effect_strength(&self, progress: f32) -> f32 {
+		let peak = self.reset_at.clamp(0.0, 1.0);
+		let from_peak = if progress < peak {
+			(peak - progress) / peak.max(f32::EPSILON)
+		} else {
+			(progress - peak) / (1.0 - peak).max(f32::EPSILON)
+		};
+		((1.0 - from_peak) * 8.0).clamp(0.0, 1.0)
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum Easing {
+	Linear,
+	/// Slow at either end and fast in the middle.
+	#[default]
+	Smooth,
+	/// Jumps between this many evenly spaced points.
+	Steps(u32),
+}
+
+impl Easing {
+	pub fn ease(self, t: f32) -> f32 {
+		match self {
+			Easing::Linear => t,
+			Easing::Smooth => 0.5 - (t * PI).cos() * 0.5,
+			Easing::Steps(0) => t,
+			Easing::Steps(n) => (t * n as f32).floor() / n as f32,
+		}
+	}
+}
+
+/// Named transitions [`ResetLoop`](crate::happens::ResetLoop) can pick from.
+#[derive(Resource, Clone, Debug, Reflect, Deref, DerefMut)]
+#[reflect(Resource)]
+pub struct LoopTransitions(pub HashMap<Str, LoopTransition>);
+
+impl Default for LoopTransitions {
+	fn default() -> Self {
+		Self(
+			[
+				(DEFAULT_TRANSITION.into(), LoopTransition::default()),
+				("instant".into(), LoopTransition::instant()),
+				("slow_rewind".into(), LoopTransition::slow_rewind()),
+				("glitch".into(), LoopTransition::glitch()),
+			]
+			.into_iter()
+			.collect(),
+		)
+	}
+}
+
+impl LoopTransitions {
+	/// Transition names that [`ResetLoop`]s in `timeline` use but that aren't in here, along with
+	/// when they're used. Includes those in the causes of triggers the timeline spawns.
+	pub fn unknown_in(&self, timeline: &Timeline) -> Vec<(LoopTime, Str)> {
+		let mut unknown = Vec::new();
+		for (t, moment) in &timeline.moments {
+			for action in moment.happenings.iter().flat_map(|h| &h.actions) {
+				let causes = action
+					.as_reflect()
+					.downcast_ref::<SpawnTrigger>()
+					.map_or(&[][..], |spawn| &spawn.trigger.causes[..]);
+				for action in std::iter::once(action).chain(causes) {
+					let Some(name) = action
+						.as_reflect()
+						.downcast_ref::<ResetLoop>()
+						.and_then(|reset| reset.transition)
+					else {
+						continue;
+					};
+					if !self.contains_key(&name) {
+						unknown.push((*t, name));
+					}
+				}
+			}
+		}
+		unknown
+	}
+}
+
+/// The transition [`seek`](super::seek) is currently playing.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ActiveTransition {
+	pub transition: LoopTransition,
+	pub elapsed: Duration,
+	/// Whether the world has been reset yet.
+	pub reset_done: bool,
+}
+
+impl ActiveTransition {
+	pub fn progress(&self) -> f32 {
+		self.transition.progress(self.elapsed)
+	}
+
+	pub fn effect_strength(&self) -> f32 {
+		self.transition.effect_strength(self.progress())
+	}
+}
+
+/// Starts seeking the loop to `to` using the transition named `name`, or the default one.
+pub fn start_transition(world: &mut World, to: LoopTime, name: Option<Str>) {
+	let name = name.unwrap_or_else(|| DEFAULT_TRANSITION.into());
+	let transition = world
+		.get_resource::<LoopTransitions>()
+		.and_then(|transitions| transitions.get(&name).cloned())
+		.unwrap_or_else(|| {
+			error!("No loop transition named {name}");
+			default()
+		});
+	world.insert_resource(ActiveTransition {
+		transition,
+		elapsed: Duration::ZERO,
+		reset_done: false,
+	});
+	let mut tloop = world.resource_mut::<TimeLoop>();
+	let curr = tloop.curr.1;
+	tloop.resetting_from = curr;
+	tloop.resetting_to = to;
+	world
+		.resource_mut::<NextState<GameState>>()
+		.set(GameState::ResettingLoop);
+}
@@ -1,11 +1,45 @@
-use crate::data::ui::{default_interact_msg, InteractIcon, InteractSign, InteractText};
-use bevy::prelude::*;
+use crate::{
+	data::{
+		tl::LoopTime,
+		ui::{
+			default_interact_msg, InteractIcon, InteractImage, InteractKey, InteractSign,
+			InteractText, PauseButton, PauseMenu,
+		},
+	},
+	happens::ResetLoop,
+	time_graph::InteractFocus,
+	GameState,
+};
+use bevy::{app::AppExit, prelude::*};
+
+#[cfg(feature = "debugging")]
+pub mod scrubber;
+
+pub mod controls;
+pub mod dialogue;
+
+pub const BUTTON_COLOR: Color = Color::rgb(0.1, 0.1, 0.1);
+pub const BUTTON_HOVERED_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+pub const BUTTON_PRESSED_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+pub const FOCUS_COLOR: Color = Color::rgb(1.0, 0.85, 0.5);
 
 pub struct GameUiPlugin;
 
 impl Plugin for GameUiPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(Startup, setup);
+		app.add_systems(Startup, setup)
+			.add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+			.add_systems(OnExit(GameState::Paused), despawn_pause_menu)
+			.add_systems(
+				Update,
+				(
+					pause_menu_buttons.run_if(in_state(GameState::Paused)),
+					highlight_interact_focus.run_if(in_state(GameState::Running)),
+				),
+			);
+		app.add_plugins((controls::ControlsPlugin, dialogue::DialoguePlugin));
+		#[cfg(feature = "debugging")]
+		app.add_plugins(scrubber::ScrubberPlugin);
 	}
 
 	fn finish(&self, app: &mut App) {
@@ -30,15 +64,37 @@ pub fn setup(mut cmds: Commands, interact_icon: Res<InteractIcon>) {
 		InteractSign,
 	))
 	.with_children(|cmds| {
-		cmds.spawn(ImageBundle {
-			image: UiImage::new(interact_icon.0.clone()),
-			style: Style {
-				margin: UiRect::all(Val::Px(16.0)),
-				min_width: Val::Px(32.0),
+		cmds.spawn((
+			ImageBundle {
+				image: UiImage::new(interact_icon.0.clone()),
+				style: Style {
+					margin: UiRect::all(Val::Px(16.0)),
+					min_width: Val::Px(32.0),
+					..default()
+				},
 				..default()
 			},
-			..default()
-		});
+			InteractImage,
+		));
+		cmds.spawn((
+			TextBundle {
+				text: Text::from_section(
+					"E",
+					TextStyle {
+						font_size: 48.0,
+						..default()
+					},
+				),
+				style: Style {
+					display: Display::None,
+					align_self: AlignSelf::Center,
+					margin: UiRect::all(Val::Px(16.0)),
+					..default()
+				},
+				..default()
+			},
+			InteractKey,
+		));
 		cmds.spawn((
 			TextBundle {
 				text: Text::from_section(
@@ -59,3 +115,112 @@ pub fn setup(mut cmds: Commands, interact_icon: Res<InteractIcon>) {
 		));
 	});
 }
+
+pub fn spawn_pause_menu(mut cmds: Commands) {
+	cmds.spawn((
+		NodeBundle {
+			style: Style {
+				width: Val::Percent(100.0),
+				height: Val::Percent(100.0),
+				flex_direction: FlexDirection::Column,
+				align_items: AlignItems::Center,
+				justify_content: JustifyContent::Center,
+				row_gap: Val::Px(16.0),
+				..default()
+			},
+			background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+			z_index: ZIndex::Global(10),
+			..default()
+		},
+		PauseMenu,
+	))
+	.with_children(|cmds| {
+		cmds.spawn(TextBundle::from_section(
+			"Paused",
+			TextStyle {
+				font_size: 64.0,
+				..default()
+			},
+		));
+		for button in [
+			PauseButton::Resume,
+			PauseButton::Controls,
+			PauseButton::RestartLoop,
+			PauseButton::Quit,
+		] {
+			cmds.spawn((
+				ButtonBundle {
+					style: Style {
+						width: Val::Px(320.0),
+						padding: UiRect::all(Val::Px(12.0)),
+						justify_content: JustifyContent::Center,
+						..default()
+					},
+					background_color: BUTTON_COLOR.into(),
+					..default()
+				},
+				button,
+			))
+			.with_children(|cmds| {
+				cmds.spawn(TextBundle::from_section(
+					button.label(),
+					TextStyle {
+						font_size: 36.0,
+						..default()
+					},
+				));
+			});
+		}
+	});
+}
+
+pub fn despawn_pause_menu(mut cmds: Commands, q: Query<Entity, With<PauseMenu>>) {
+	for id in &q {
+		cmds.entity(id).despawn_recursive();
+	}
+}
+
+pub fn pause_menu_buttons(
+	mut cmds: Commands,
+	mut q: Query<(&Interaction, &PauseButton, &mut BackgroundColor), Changed<Interaction>>,
+	mut next_state: ResMut<NextState<GameState>>,
+	mut exit: EventWriter<AppExit>,
+) {
+	for (interaction, button, mut bg) in &mut q {
+		match interaction {
+			Interaction::Pressed => {
+				*bg = BUTTON_PRESSED_COLOR.into();
+				match button {
+					PauseButton::Resume => next_state.set(GameState::Running),
+					PauseButton::Controls => controls::spawn_controls_menu(&mut cmds),
+					PauseButton::RestartLoop => cmds.add(ResetLoop {
+						to: LoopTime::EPOCH,
+						..default()
+					}),
+					PauseButton::Quit => {
+						exit.send(AppExit);
+					}
+				}
+			}
+			Interaction::Hovered => *bg = BUTTON_HOVERED_COLOR.into(),
+			Interaction::None => *bg = BUTTON_COLOR.into(),
+		}
+	}
+}
+
+/// Rings the focused interactable when there is more than one in reach.
+pub fn highlight_interact_focus(
+	focus: Res<InteractFocus>,
+	q: Query<&GlobalTransform>,
+	mut gizmos: Gizmos,
+	t: Res<Time>,
+) {
+	if focus.candidates.len() < 2 {
+		return;
+	}
+	let Some(xform) = focus.focused.and_then(|id| q.get(id).ok()) else {
+		return;
+	};
+	let radius = 0.4 + (t.elapsed_seconds() * 4.0).sin() * 0.03;
+	gizmos.circle(xform.translation(), Direction3d::Z, radius, FOCUS_COLOR);
+}
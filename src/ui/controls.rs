@@ -0,0 +1,423 @@
+//! Settings screen for rebinding each [`Action`], opened from the pause menu.
+//!
+//! Bindings are saved to [`storage`](crate::save::storage) as soon as they change.
+
+use super::{BUTTON_COLOR, BUTTON_HOVERED_COLOR, BUTTON_PRESSED_COLOR};
+use crate::{
+	data::ui::{
+		BindingText, ControlsButton, ControlsMenu, InteractIcon, InteractImage, InteractKey,
+	},
+	player::{default_input_map, player_entity::Root, save_input_map, Action},
+	GameState,
+};
+use bevy::{asset::LoadState, input::gamepad::Gamepads, prelude::*};
+use leafwing_input_manager::{prelude::*, user_input::InputKind};
+use sond_bevy_enum_components::WithVariant;
+
+pub const REBINDING_COLOR: Color = Color::rgb(0.5, 0.35, 0.1);
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(OnExit(GameState::Paused), despawn_controls_menu)
+			.add_systems(
+				Update,
+				(
+					controls_menu_buttons,
+					capture_binding.run_if(resource_exists::<Rebinding>),
+					update_binding_texts,
+				)
+					.chain()
+					.run_if(in_state(GameState::Paused)),
+			)
+			.add_systems(
+				Update,
+				(update_interact_icon, interact_icon_fallback).chain(),
+			);
+	}
+}
+
+/// The action in the [`ControlsMenu`] waiting for a new binding.
+#[derive(Resource, Clone, Debug)]
+pub struct Rebinding {
+	pub action: Action,
+	/// Keys pressed so far, for actions like [`Action::Move`] that need one per direction.
+	pub keys: Vec<KeyCode>,
+}
+
+pub fn spawn_controls_menu(cmds: &mut Commands) {
+	cmds.spawn((
+		NodeBundle {
+			style: Style {
+				width: Val::Percent(100.0),
+				height: Val::Percent(100.0),
+				flex_direction: FlexDirection::Column,
+				align_items: AlignItems::Center,
+				justify_content: JustifyContent::Center,
+				row_gap: Val::Px(12.0),
+				..default()
+			},
+			background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+			// Over the pause menu, which stays underneath for when this is closed.
+			z_index: ZIndex::Global(11),
+			..default()
+		},
+		ControlsMenu,
+	))
+	.with_children(|cmds| {
+		cmds.spawn(TextBundle::from_section(
+			"Controls",
+			TextStyle {
+				font_size: 64.0,
+				..default()
+			},
+		));
+		for action in [
+			Action::Move,
+			Action::Jump,
+			Action::Dash,
+			Action::Interact,
+			Action::CycleInteract,
+			Action::Pause,
+		] {
+			cmds.spawn(NodeBundle {
+				style: Style {
+					width: Val::Px(720.0),
+					align_items: AlignItems::Center,
+					column_gap: Val::Px(16.0),
+					..default()
+				},
+				..default()
+			})
+			.with_children(|cmds| {
+				cmds.spawn(TextBundle {
+					text: Text::from_section(
+						format!("{action:?}"),
+						TextStyle {
+							font_size: 32.0,
+							..default()
+						},
+					),
+					style: Style {
+						width: Val::Px(160.0),
+						..default()
+					},
+					..default()
+				});
+				cmds.spawn((
+					TextBundle {
+						text: Text::from_section(
+							"",
+							TextStyle {
+								font_size: 24.0,
+								..default()
+							},
+						),
+						style: Style {
+							flex_grow: 1.0,
+							..default()
+						},
+						..default()
+					},
+					BindingText(action),
+				));
+				spawn_button(cmds, ControlsButton::Rebind(action), "Rebind");
+			});
+		}
+		spawn_button(cmds, ControlsButton::ResetDefaults, "Reset to defaults");
+		spawn_button(cmds, ControlsButton::Back, "Back");
+	});
+}
+
+fn spawn_button(cmds: &mut ChildBuilder, button: ControlsButton, label: &str) {
+	cmds.spawn((
+		ButtonBundle {
+			style: Style {
+				min_width: Val::Px(160.0),
+				padding: UiRect::all(Val::Px(8.0)),
+				justify_content: JustifyContent::Center,
+				..default()
+			},
+			background_color: BUTTON_COLOR.into(),
+			..default()
+		},
+		button,
+	))
+	.with_children(|cmds| {
+		cmds.spawn(TextBundle::from_section(
+			label,
+			TextStyle {
+				font_size: 28.0,
+				..default()
+			},
+		));
+	});
+}
+
+pub fn despawn_controls_menu(mut cmds: Commands, q: Query<Entity, With<ControlsMenu>>) {
+	cmds.remove_resource::<Rebinding>();
+	for id in &q {
+		cmds.entity(id).despawn_recursive();
+	}
+}
+
+pub fn controls_menu_buttons(
+	mut cmds: Commands,
+	mut q: Query<(&Interaction, &ControlsButton, &mut BackgroundColor), Changed<Interaction>>,
+	mut player: Query<&mut InputMap<Action>, WithVariant<Root>>,
+	menu: Query<Entity, With<ControlsMenu>>,
+	rebinding: Option<Res<Rebinding>>,
+) {
+	for (interaction, button, mut bg) in &mut q {
+		match interaction {
+			Interaction::Pressed => {
+				*bg = BUTTON_PRESSED_COLOR.into();
+				match *button {
+					ControlsButton::Rebind(action) => cmds.insert_resource(Rebinding {
+						action,
+						keys: Vec::new(),
+					}),
+					ControlsButton::ResetDefaults => {
+						cmds.remove_resource::<Rebinding>();
+						if let Ok(mut map) = player.get_single_mut() {
+							*map = default_input_map();
+							save_input_map(&map);
+						}
+					}
+					ControlsButton::Back => {
+						cmds.remove_resource::<Rebinding>();
+						for id in &menu {
+							cmds.entity(id).despawn_recursive();
+						}
+					}
+				}
+			}
+			Interaction::Hovered => *bg = BUTTON_HOVERED_COLOR.into(),
+			Interaction::None => {
+				let waiting = matches!(
+					(&rebinding, button),
+					(Some(rebinding), ControlsButton::Rebind(action)) if rebinding.action == *action
+				);
+				*bg = if waiting {
+					REBINDING_COLOR.into()
+				} else {
+					BUTTON_COLOR.into()
+				};
+			}
+		}
+	}
+}
+
+/// Binds the next key or gamepad button pressed to the [`Rebinding`] action, replacing its
+/// existing bindings on the same device. [`Action::Move`] takes four keys: up, down, left, then
+/// right.
+pub fn capture_binding(
+	mut cmds: Commands,
+	mut rebinding: ResMut<Rebinding>,
+	keys: Res<ButtonInput<KeyCode>>,
+	buttons: Res<ButtonInput<GamepadButton>>,
+	mut player: Query<&mut InputMap<Action>, WithVariant<Root>>,
+) {
+	let Ok(mut map) = player.get_single_mut() else {
+		return;
+	};
+	let action = rebinding.action;
+	if action == Action::Move {
+		if let Some(key) = keys.get_just_pressed().next() {
+			rebinding.keys.push(*key);
+		}
+		let &[up, down, left, right] = &rebinding.keys[..] else {
+			return;
+		};
+		let dpad = VirtualDPad {
+			up: InputKind::PhysicalKey(up),
+			down: InputKind::PhysicalKey(down),
+			left: InputKind::PhysicalKey(left),
+			right: InputKind::PhysicalKey(right),
+		};
+		replace_binding(&mut map, action, dpad.into());
+	} else if let Some(key) = keys.get_just_pressed().next() {
+		replace_binding(&mut map, action, (*key).into());
+	} else if let Some(button) = buttons.get_just_pressed().next() {
+		replace_binding(&mut map, action, button.button_type.into());
+	} else {
+		return;
+	}
+	save_input_map(&map);
+	cmds.remove_resource::<Rebinding>();
+}
+
+/// Binds `input` to `action` in place of any bindings from the same device.
+pub fn replace_binding(map: &mut InputMap<Action>, action: Action, input: UserInput) {
+	let keyboard = is_keyboard(&input);
+	let kept = map
+		.get(&action)
+		.into_iter()
+		.flatten()
+		.filter(|it| is_keyboard(it) != keyboard)
+		.cloned()
+		.collect::<Vec<_>>();
+	map.clear_action(&action);
+	for it in kept {
+		map.insert(action, it);
+	}
+	map.insert(action, input);
+}
+
+/// Whether `input` is read from the keyboard or mouse rather than a gamepad.
+pub fn is_keyboard(input: &UserInput) -> bool {
+	let kind_is_keyboard = |kind: &InputKind| {
+		!matches!(
+			kind,
+			InputKind::GamepadButton(_) | InputKind::SingleAxis(_) | InputKind::DualAxis(_)
+		)
+	};
+	match input {
+		UserInput::Single(kind) => kind_is_keyboard(kind),
+		UserInput::Chord(kinds) => kinds.iter().any(kind_is_keyboard),
+		UserInput::VirtualDPad(dpad) => kind_is_keyboard(&dpad.up),
+		UserInput::VirtualAxis(axis) => kind_is_keyboard(&axis.negative),
+	}
+}
+
+/// `KeyE` as `E`, `Digit1` as `1`, and so on.
+pub fn key_name(key: KeyCode) -> String {
+	let name = format!("{key:?}");
+	name.strip_prefix("Key")
+		.or_else(|| name.strip_prefix("Digit"))
+		.unwrap_or(&name)
+		.to_owned()
+}
+
+pub fn binding_name(input: &UserInput) -> String {
+	let kind_name = |kind: &InputKind| match kind {
+		InputKind::PhysicalKey(key) => key_name(*key),
+		InputKind::GamepadButton(button) => format!("{button:?}"),
+		other => format!("{other:?}"),
+	};
+	match input {
+		UserInput::Single(kind) => kind_name(kind),
+		UserInput::Chord(kinds) => kinds.iter().map(kind_name).collect::<Vec<_>>().join("+"),
+		UserInput::VirtualDPad(dpad) => [&dpad.up, &dpad.left, &dpad.down, &dpad.right]
+			.into_iter()
+			.map(kind_name)
+			.collect::<Vec<_>>()
+			.join("/"),
+		UserInput::VirtualAxis(axis) => {
+			format!(
+				"{}/{}",
+				kind_name(&axis.negative),
+				kind_name(&axis.positive)
+			)
+		}
+	}
+}
+
+/// Where the icon for `input` would be, if it is a single key or button.
+pub fn icon_path(input: &UserInput) -> Option<String> {
+	match input {
+		UserInput::Single(InputKind::PhysicalKey(key)) => Some(format!("ui/keys/{key:?}.png")),
+		UserInput::Single(InputKind::GamepadButton(button)) => {
+			Some(format!("ui/buttons/{button:?}.png"))
+		}
+		_ => None,
+	}
+}
+
+pub fn update_binding_texts(
+	player: Query<Ref<InputMap<Action>>, WithVariant<Root>>,
+	mut q: Query<(Ref<BindingText>, &mut Text)>,
+	rebinding: Option<Res<Rebinding>>,
+) {
+	let Ok(map) = player.get_single() else {
+		return;
+	};
+	let rebinding_changed = rebinding.as_ref().map_or(false, |it| it.is_changed());
+	for (binding, mut text) in &mut q {
+		if !map.is_changed() && !binding.is_added() && !rebinding_changed {
+			continue;
+		}
+		text.sections[0].value = match &rebinding {
+			Some(rebinding) if rebinding.action == binding.0 => match binding.0 {
+				Action::Move => {
+					let dirs = ["up", "down", "left", "right"];
+					let next = dirs.get(rebinding.keys.len()).unwrap_or(&"right");
+					format!("Press a key for {next}...")
+				}
+				_ => "Press a key or button...".into(),
+			},
+			_ => map
+				.get(&binding.0)
+				.into_iter()
+				.flatten()
+				.map(binding_name)
+				.collect::<Vec<_>>()
+				.join(", "),
+		};
+	}
+}
+
+/// Points the [`InteractSign`](crate::data::ui::InteractSign) at the icon for whatever
+/// `Interact` is bound to, preferring gamepad buttons while a gamepad is connected.
+pub fn update_interact_icon(
+	player: Query<Ref<InputMap<Action>>, WithVariant<Root>>,
+	gamepads: Res<Gamepads>,
+	srv: Res<AssetServer>,
+	mut icon: ResMut<InteractIcon>,
+	mut images: Query<&mut UiImage, With<InteractImage>>,
+	mut key: Query<&mut Text, With<InteractKey>>,
+) {
+	let Ok(map) = player.get_single() else {
+		return;
+	};
+	if !map.is_changed() && !gamepads.is_changed() {
+		return;
+	}
+	let want_keyboard = gamepads.iter().next().is_none();
+	let inputs = map.get(&Action::Interact).cloned().unwrap_or_default();
+	let Some(input) = inputs
+		.iter()
+		.find(|it| is_keyboard(it) == want_keyboard)
+		.or_else(|| inputs.first())
+	else {
+		return;
+	};
+	if let Some(path) = icon_path(input) {
+		icon.0 = srv.load(path);
+	} else {
+		icon.0 = default();
+	}
+	for mut image in &mut images {
+		image.texture = icon.0.clone();
+	}
+	for mut text in &mut key {
+		text.sections[0].value = binding_name(input);
+	}
+}
+
+/// Shows the key name instead of the icon if there's no icon for the key.
+pub fn interact_icon_fallback(
+	icon: Res<InteractIcon>,
+	srv: Res<AssetServer>,
+	mut images: Query<&mut Style, (With<InteractImage>, Without<InteractKey>)>,
+	mut key: Query<&mut Style, (With<InteractKey>, Without<InteractImage>)>,
+) {
+	let failed = icon.0 == Handle::default()
+		|| matches!(srv.get_load_state(icon.0.id()), Some(LoadState::Failed));
+	let (image_display, key_display) = if failed {
+		(Display::None, Display::Flex)
+	} else {
+		(Display::Flex, Display::None)
+	};
+	for mut style in &mut images {
+		if style.display != image_display {
+			style.display = image_display;
+		}
+	}
+	for mut style in &mut key {
+		if style.display != key_display {
+			style.display = key_display;
+		}
+	}
+}
@@ -0,0 +1,396 @@
+//! Dialogue box for [`ShowDialogue`](crate::happens::ShowDialogue).
+//!
+//! `Interact` reveals the rest of a line or moves on to the next one. `Jump` skips ahead over
+//! lines already read in an earlier loop.
+
+use super::{BUTTON_COLOR, BUTTON_HOVERED_COLOR, BUTTON_PRESSED_COLOR};
+use crate::{
+	data::{
+		dlg::{ActiveDialogue, Dialogue, SeenDialogue},
+		tl::TimeLoop,
+		ui::{
+			ChoiceButton, DialogueBox, DialogueChoices, DialogueSeenTag, DialogueSpeaker,
+			DialogueText,
+		},
+	},
+	player::{player_entity::Root, Action},
+	GameState,
+};
+use bevy::{
+	asset::{AssetPath, LoadState},
+	prelude::*,
+};
+use leafwing_input_manager::prelude::*;
+use sond_bevy_enum_components::WithVariant;
+
+/// How fast lines are typed out.
+pub const CHARS_PER_SEC: f32 = 40.0;
+pub const SEEN_COLOR: Color = Color::rgb(0.55, 0.55, 0.55);
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Startup, spawn_dialogue_box).add_systems(
+			Update,
+			(
+				start_dialogue,
+				(advance_dialogue, choose).run_if(in_state(GameState::Running)),
+				update_dialogue_box,
+			)
+				.chain(),
+		);
+	}
+}
+
+pub fn spawn_dialogue_box(mut cmds: Commands) {
+	cmds.spawn((
+		NodeBundle {
+			style: Style {
+				position_type: PositionType::Absolute,
+				bottom: Val::Px(32.0),
+				left: Val::Percent(15.0),
+				width: Val::Percent(70.0),
+				min_height: Val::Px(160.0),
+				flex_direction: FlexDirection::Column,
+				padding: UiRect::all(Val::Px(16.0)),
+				row_gap: Val::Px(8.0),
+				..default()
+			},
+			background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+			z_index: ZIndex::Global(5),
+			visibility: Visibility::Hidden,
+			..default()
+		},
+		DialogueBox,
+	))
+	.with_children(|cmds| {
+		cmds.spawn(NodeBundle {
+			style: Style {
+				column_gap: Val::Px(16.0),
+				..default()
+			},
+			..default()
+		})
+		.with_children(|cmds| {
+			cmds.spawn((
+				TextBundle::from_section(
+					"",
+					TextStyle {
+						font_size: 28.0,
+						color: Color::rgb(1.0, 0.85, 0.5),
+						..default()
+					},
+				),
+				DialogueSpeaker,
+			));
+			cmds.spawn((
+				TextBundle::from_section(
+					"Seen",
+					TextStyle {
+						font_size: 20.0,
+						color: SEEN_COLOR,
+						..default()
+					},
+				)
+				.with_style(Style {
+					align_self: AlignSelf::Center,
+					display: Display::None,
+					..default()
+				}),
+				DialogueSeenTag,
+			));
+		});
+		cmds.spawn((
+			TextBundle::from_section(
+				"",
+				TextStyle {
+					font_size: 32.0,
+					..default()
+				},
+			),
+			DialogueText,
+		));
+		cmds.spawn((
+			NodeBundle {
+				style: Style {
+					flex_direction: FlexDirection::Column,
+					row_gap: Val::Px(4.0),
+					..default()
+				},
+				..default()
+			},
+			DialogueChoices,
+		));
+	});
+}
+
+/// The path of the timeline the loop is in, for [`Line::shown_in`](crate::data::dlg::Line).
+fn curr_timeline(srv: &AssetServer, tloop: &TimeLoop) -> Option<AssetPath<'static>> {
+	srv.get_path(tloop.curr.0).map(AssetPath::into_owned)
+}
+
+/// Moves `active` to line `to`, or closes the dialogue if there's nowhere to go.
+fn go_to_line(
+	cmds: &mut Commands,
+	active: &mut ActiveDialogue,
+	dlg: &Dialogue,
+	seen: &SeenDialogue,
+	to: Option<usize>,
+) {
+	let Some(i) = to else {
+		cmds.remove_resource::<ActiveDialogue>();
+		return;
+	};
+	active.line = Some(i);
+	active.seen = seen.contains(&(active.path.clone(), dlg.line_key(i)));
+	active.revealed = if active.seen {
+		dlg.lines[i].text.chars().count() as f32
+	} else {
+		0.0
+	};
+}
+
+/// Picks the first line once the dialogue has loaded.
+pub fn start_dialogue(
+	mut cmds: Commands,
+	active: Option<ResMut<ActiveDialogue>>,
+	dialogues: Res<Assets<Dialogue>>,
+	seen: Res<SeenDialogue>,
+	srv: Res<AssetServer>,
+	tloop: Res<TimeLoop>,
+) {
+	let Some(mut active) = active else {
+		return;
+	};
+	if active.line.is_some() {
+		return;
+	}
+	let Some(dlg) = dialogues.get(&active.handle) else {
+		if let Some(LoadState::Failed) = srv.get_load_state(active.handle.id()) {
+			error!("Failed to load dialogue {}", active.path);
+			cmds.remove_resource::<ActiveDialogue>();
+		}
+		return;
+	};
+	let timeline = curr_timeline(&srv, &tloop);
+	let first = match active.start {
+		Some(label) => dlg.find_label(label, timeline.as_ref()),
+		None => dlg.next_line(0, timeline.as_ref()),
+	};
+	go_to_line(&mut cmds, &mut active, dlg, &seen, first);
+}
+
+/// Types out the current line and moves on from it when the player presses `Interact`.
+pub fn advance_dialogue(
+	mut cmds: Commands,
+	active: Option<ResMut<ActiveDialogue>>,
+	player: Query<&ActionState<Action>, WithVariant<Root>>,
+	dialogues: Res<Assets<Dialogue>>,
+	mut seen: ResMut<SeenDialogue>,
+	srv: Res<AssetServer>,
+	tloop: Res<TimeLoop>,
+	t: Res<Time>,
+) {
+	let Some(mut active) = active else {
+		return;
+	};
+	let (Some(i), Some(dlg)) = (active.line, dialogues.get(&active.handle)) else {
+		return;
+	};
+	let Some(line) = dlg.lines.get(i) else {
+		cmds.remove_resource::<ActiveDialogue>();
+		return;
+	};
+	let Ok(inputs) = player.get_single() else {
+		return;
+	};
+
+	let len = line.text.chars().count() as f32;
+	if active.revealed < len {
+		if inputs.just_pressed(&Action::Interact) {
+			active.revealed = len;
+		} else {
+			active.revealed = (active.revealed + t.delta_seconds() * CHARS_PER_SEC).min(len);
+		}
+		if active.revealed < len {
+			return;
+		}
+	}
+	seen.insert((active.path.clone(), dlg.line_key(i)));
+
+	let timeline = curr_timeline(&srv, &tloop);
+	let next = |i: usize| {
+		let line = &dlg.lines[i];
+		if line.end {
+			None
+		} else if let Some(label) = line.goto {
+			dlg.find_label(label, timeline.as_ref())
+		} else {
+			dlg.next_line(i + 1, timeline.as_ref())
+		}
+	};
+
+	if inputs.just_pressed(&Action::Jump) {
+		// Skip every line read in an earlier loop, up to the next choice.
+		// Bounded in case `goto`s loop back on themselves.
+		let mut to = Some(i);
+		for _ in 0..dlg.lines.len() {
+			let Some(j) = to else {
+				break;
+			};
+			let read = seen.contains(&(active.path.clone(), dlg.line_key(j)));
+			if !read || !dlg.lines[j].choices.is_empty() {
+				break;
+			}
+			to = next(j);
+		}
+		if to != Some(i) {
+			go_to_line(&mut cmds, &mut active, dlg, &seen, to);
+		}
+		return;
+	}
+
+	if line.choices.is_empty() && inputs.just_pressed(&Action::Interact) {
+		go_to_line(&mut cmds, &mut active, dlg, &seen, next(i));
+	}
+}
+
+/// Runs the picked [`Choice`](crate::data::dlg::Choice) and continues from its `goto`.
+pub fn choose(
+	mut cmds: Commands,
+	mut q: Query<(&Interaction, &ChoiceButton, &mut BackgroundColor), Changed<Interaction>>,
+	active: Option<ResMut<ActiveDialogue>>,
+	dialogues: Res<Assets<Dialogue>>,
+	seen: Res<SeenDialogue>,
+	srv: Res<AssetServer>,
+	tloop: Res<TimeLoop>,
+) {
+	let Some(mut active) = active else {
+		return;
+	};
+	let (Some(i), Some(dlg)) = (active.line, dialogues.get(&active.handle)) else {
+		return;
+	};
+	for (interaction, button, mut bg) in &mut q {
+		match interaction {
+			Interaction::Pressed => {
+				*bg = BUTTON_PRESSED_COLOR.into();
+				let Some(choice) = dlg.lines.get(i).and_then(|line| line.choices.get(button.0))
+				else {
+					continue;
+				};
+				for to_do in choice.causes.iter() {
+					to_do.apply(cmds.reborrow());
+				}
+				let timeline = curr_timeline(&srv, &tloop);
+				let to = choice
+					.goto
+					.and_then(|label| dlg.find_label(label, timeline.as_ref()));
+				go_to_line(&mut cmds, &mut active, dlg, &seen, to);
+				return;
+			}
+			Interaction::Hovered => *bg = BUTTON_HOVERED_COLOR.into(),
+			Interaction::None => *bg = BUTTON_COLOR.into(),
+		}
+	}
+}
+
+pub fn update_dialogue_box(
+	mut cmds: Commands,
+	active: Option<Res<ActiveDialogue>>,
+	dialogues: Res<Assets<Dialogue>>,
+	mut dlg_box: Query<&mut Visibility, With<DialogueBox>>,
+	mut speaker: Query<&mut Text, (With<DialogueSpeaker>, Without<DialogueText>)>,
+	mut text: Query<&mut Text, (With<DialogueText>, Without<DialogueSpeaker>)>,
+	mut seen_tag: Query<&mut Style, With<DialogueSeenTag>>,
+	choices: Query<Entity, With<DialogueChoices>>,
+	mut shown: Local<Option<(AssetId<Dialogue>, usize, usize)>>,
+) {
+	let line = active.as_ref().and_then(|active| {
+		let i = active.line?;
+		Some((active, i, dialogues.get(&active.handle)?.lines.get(i)?))
+	});
+	let Some((active, i, line)) = line else {
+		for mut vis in &mut dlg_box {
+			if *vis != Visibility::Hidden {
+				*vis = Visibility::Hidden;
+			}
+		}
+		if shown.take().is_some() {
+			for id in &choices {
+				cmds.entity(id).despawn_descendants();
+			}
+		}
+		return;
+	};
+	for mut vis in &mut dlg_box {
+		if *vis != Visibility::Visible {
+			*vis = Visibility::Visible;
+		}
+	}
+
+	let id = active.handle.id();
+	let revealed = active.revealed as usize;
+	if *shown == Some((id, i, revealed)) {
+		return;
+	}
+	let choices_were_shown = shown.map_or(false, |(shown_id, j, n)| {
+		shown_id == id && j == i && n >= line.text.chars().count()
+	});
+	*shown = Some((id, i, revealed));
+
+	for mut speaker in &mut speaker {
+		speaker.sections[0].value = line.speaker.clone().unwrap_or_default();
+	}
+	for mut text in &mut text {
+		text.sections[0].value = line.text.chars().take(revealed).collect();
+		text.sections[0].style.color = if active.seen {
+			SEEN_COLOR
+		} else {
+			Color::WHITE
+		};
+	}
+	for mut style in &mut seen_tag {
+		style.display = if active.seen {
+			Display::Flex
+		} else {
+			Display::None
+		};
+	}
+
+	let fully_revealed = revealed >= line.text.chars().count();
+	if fully_revealed && choices_were_shown {
+		return;
+	}
+	for id in &choices {
+		cmds.entity(id).despawn_descendants();
+		if !fully_revealed {
+			continue;
+		}
+		cmds.entity(id).with_children(|cmds| {
+			for (n, choice) in line.choices.iter().enumerate() {
+				cmds.spawn((
+					ButtonBundle {
+						style: Style {
+							padding: UiRect::all(Val::Px(8.0)),
+							..default()
+						},
+						background_color: BUTTON_COLOR.into(),
+						..default()
+					},
+					ChoiceButton(n),
+				))
+				.with_children(|cmds| {
+					cmds.spawn(TextBundle::from_section(
+						choice.text.clone(),
+						TextStyle {
+							font_size: 28.0,
+							..default()
+						},
+					));
+				});
+			}
+		});
+	}
+}
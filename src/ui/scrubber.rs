@@ -0,0 +1,267 @@
+//! Debug overlay showing the current timeline and the ones it branches from or merges into.
+//!
+//! Toggle with `T`. Clicking a moment seeks the loop to it.
+
+use crate::{
+	data::tl::{LoopTime, TimeLoop, Timeline},
+	happens::ResetLoop,
+	GameState,
+};
+use bevy::{prelude::*, utils::HashSet};
+
+/// Shortest span of loop time the tracks cover, so a nearly empty timeline isn't all bunched up.
+pub const MIN_SPAN: LoopTime = LoopTime::from_secs(60);
+pub const TRACK_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const TICK_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+pub const DISABLED_TICK_COLOR: Color = Color::rgb(0.35, 0.35, 0.35);
+pub const CURSOR_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+
+pub struct ScrubberPlugin;
+
+impl Plugin for ScrubberPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Startup, spawn_scrubber).add_systems(
+			Update,
+			(
+				toggle_scrubber,
+				rebuild_tracks,
+				move_cursors,
+				click_ticks
+					.run_if(in_state(GameState::Running).or_else(in_state(GameState::Paused))),
+			)
+				.chain(),
+		);
+	}
+}
+
+#[derive(Component, Debug)]
+pub struct ScrubberPanel;
+
+/// A moment on one of the tracks.
+#[derive(Component, Debug, Deref)]
+pub struct ScrubberTick(pub LoopTime);
+
+/// Marks `TimeLoop::curr.1` on a track `span` long.
+#[derive(Component, Debug)]
+pub struct ScrubberCursor {
+	pub span: LoopTime,
+}
+
+/// The timelines [`handle_happenings`](crate::time_graph::handle_happenings) walks from `curr`:
+/// its `branch_from` ancestors, oldest first, then `curr` itself, then what it merges into.
+pub fn tracks(timelines: &Assets<Timeline>, curr: AssetId<Timeline>) -> Vec<AssetId<Timeline>> {
+	let mut seen = HashSet::default();
+	seen.insert(curr);
+	let mut ancestors = Vec::new();
+	let mut id = curr;
+	while let Some(parent) = timelines.get(id).and_then(|tl| tl.branch_from) {
+		if !seen.insert(parent.0) {
+			break;
+		}
+		ancestors.push(parent.0);
+		id = parent.0;
+	}
+	ancestors.reverse();
+	ancestors.push(curr);
+	let mut id = curr;
+	while let Some(child) = timelines.get(id).and_then(|tl| tl.merge_into) {
+		if !seen.insert(child.0) {
+			break;
+		}
+		ancestors.push(child.0);
+		id = child.0;
+	}
+	ancestors
+}
+
+pub fn spawn_scrubber(mut cmds: Commands) {
+	cmds.spawn((
+		NodeBundle {
+			style: Style {
+				position_type: PositionType::Absolute,
+				bottom: Val::Px(0.0),
+				width: Val::Percent(100.0),
+				flex_direction: FlexDirection::Column,
+				padding: UiRect::all(Val::Px(8.0)),
+				row_gap: Val::Px(20.0),
+				..default()
+			},
+			background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+			// Above the pause menu, so the loop can be scrubbed while paused.
+			z_index: ZIndex::Global(20),
+			visibility: Visibility::Hidden,
+			..default()
+		},
+		ScrubberPanel,
+	));
+}
+
+pub fn toggle_scrubber(
+	keys: Res<ButtonInput<KeyCode>>,
+	mut q: Query<&mut Visibility, With<ScrubberPanel>>,
+) {
+	if !keys.just_pressed(KeyCode::KeyT) {
+		return;
+	}
+	for mut vis in &mut q {
+		*vis = match *vis {
+			Visibility::Hidden => Visibility::Visible,
+			_ => Visibility::Hidden,
+		};
+	}
+}
+
+/// Respawns the tracks when the loop moves to another timeline, or any timeline changes.
+pub fn rebuild_tracks(
+	mut cmds: Commands,
+	panel: Query<Entity, With<ScrubberPanel>>,
+	mut events: EventReader<AssetEvent<Timeline>>,
+	timelines: Res<Assets<Timeline>>,
+	srv: Res<AssetServer>,
+	tloop: Res<TimeLoop>,
+	mut shown: Local<Option<AssetId<Timeline>>>,
+) {
+	let changed = events.read().count() > 0;
+	if !changed && *shown == Some(tloop.curr.0) {
+		return;
+	}
+	let Ok(panel) = panel.get_single() else {
+		return;
+	};
+	*shown = Some(tloop.curr.0);
+
+	let ids = tracks(&timelines, tloop.curr.0);
+	let span = ids
+		.iter()
+		.filter_map(|id| timelines.get(*id))
+		.filter_map(|tl| tl.moments.keys().next_back().copied())
+		.chain([tloop.curr.1, MIN_SPAN])
+		.max()
+		.unwrap_or(MIN_SPAN);
+
+	cmds.entity(panel)
+		.despawn_descendants()
+		.with_children(|cmds| {
+			for id in ids {
+				let Some(tl) = timelines.get(id) else {
+					continue;
+				};
+				let name = srv
+					.get_path(id)
+					.map_or_else(|| format!("{id:?}"), |path| path.to_string());
+				cmds.spawn(NodeBundle {
+					style: Style {
+						height: Val::Px(24.0),
+						column_gap: Val::Px(8.0),
+						..default()
+					},
+					..default()
+				})
+				.with_children(|cmds| {
+					cmds.spawn(TextBundle {
+						text: Text::from_section(
+							name,
+							TextStyle {
+								font_size: 16.0,
+								..default()
+							},
+						),
+						style: Style {
+							width: Val::Px(200.0),
+							..default()
+						},
+						..default()
+					});
+					cmds.spawn(NodeBundle {
+						style: Style {
+							flex_grow: 1.0,
+							..default()
+						},
+						background_color: TRACK_COLOR.into(),
+						..default()
+					})
+					.with_children(|cmds| {
+						for (t, mom) in tl.moments.iter() {
+							let left = Val::Percent(t.secs_f32() / span.secs_f32() * 100.0);
+							let color = if mom.disabled {
+								DISABLED_TICK_COLOR
+							} else {
+								TICK_COLOR
+							};
+							cmds.spawn((
+								ButtonBundle {
+									style: Style {
+										position_type: PositionType::Absolute,
+										left,
+										width: Val::Px(4.0),
+										height: Val::Percent(100.0),
+										..default()
+									},
+									background_color: color.into(),
+									..default()
+								},
+								ScrubberTick(*t),
+							));
+							let label = mom
+								.label
+								.map(|label| label.to_string())
+								.or_else(|| mom.desc.as_deref().map(str::to_owned))
+								.unwrap_or_else(|| t.to_string());
+							cmds.spawn(TextBundle {
+								text: Text::from_section(
+									label,
+									TextStyle {
+										font_size: 12.0,
+										color,
+										..default()
+									},
+								),
+								style: Style {
+									position_type: PositionType::Absolute,
+									left,
+									top: Val::Percent(100.0),
+									..default()
+								},
+								..default()
+							});
+						}
+						cmds.spawn((
+							NodeBundle {
+								style: Style {
+									position_type: PositionType::Absolute,
+									width: Val::Px(2.0),
+									height: Val::Percent(100.0),
+									..default()
+								},
+								background_color: CURSOR_COLOR.into(),
+								z_index: ZIndex::Local(1),
+								..default()
+							},
+							ScrubberCursor { span },
+						));
+					});
+				});
+			}
+		});
+}
+
+pub fn move_cursors(mut q: Query<(&mut Style, &ScrubberCursor)>, tloop: Res<TimeLoop>) {
+	for (mut style, cursor) in &mut q {
+		let fraction = (tloop.curr.1.secs_f32() / cursor.span.secs_f32()).clamp(0.0, 1.0);
+		style.left = Val::Percent(fraction * 100.0);
+	}
+}
+
+pub fn click_ticks(
+	mut cmds: Commands,
+	q: Query<(&Interaction, &ScrubberTick), Changed<Interaction>>,
+) {
+	for (interaction, tick) in &q {
+		if *interaction == Interaction::Pressed {
+			cmds.add(ResetLoop {
+				to: **tick,
+				..default()
+			});
+		}
+	}
+}
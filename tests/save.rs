@@ -0,0 +1,81 @@
+#![cfg(feature = "testing")]
+
+use bevy::prelude::*;
+use kairoi::{
+	data::tl::{Happenings, LoopTime, Moment, TimeLoop, Timeline},
+	save::{SaveFile, SavedEntities},
+	scn::Resettable,
+	testing::TimeGraphHarness,
+};
+
+#[test]
+fn only_named_entities_are_restored() {
+	let mut harness = TimeGraphHarness::builder().build();
+	let world = &mut harness.app.world;
+	let saved_at = Transform::from_xyz(1.0, 2.0, 3.0);
+	let named = world
+		.spawn((Name::new("crate"), Resettable::default(), saved_at))
+		.id();
+	let unnamed = world.spawn((Resettable::default(), saved_at)).id();
+	let save = SaveFile::capture(world).expect("the loop should be in a loaded timeline");
+	for id in [named, unnamed] {
+		world
+			.get_mut::<Transform>(id)
+			.expect("entity should still exist")
+			.translation = Vec3::ZERO;
+	}
+
+	save.restore(world);
+	// Normally waits for the player and the active area to spawn.
+	world
+		.remove_resource::<SavedEntities>()
+		.expect("entities should be left to restore later")
+		.restore(world);
+
+	assert_eq!(world.get::<Transform>(named), Some(&saved_at));
+	assert_eq!(
+		world.get::<Transform>(unnamed),
+		Some(&Transform::IDENTITY),
+		"unnamed entities can't be matched up, so they aren't saved"
+	);
+}
+
+fn labelled(label: &str) -> Happenings {
+	Happenings {
+		label: Some(label.into()),
+		..default()
+	}
+}
+
+fn moment_at(world: &mut World, tl: AssetId<Timeline>, at: LoopTime) -> &mut Moment {
+	world
+		.resource_mut::<Assets<Timeline>>()
+		.into_inner()
+		.get_mut(tl)
+		.expect("timeline should be loaded")
+		.moments
+		.entry(at)
+		.or_default()
+}
+
+#[test]
+fn happenings_flags_follow_their_labels() {
+	let mut harness = TimeGraphHarness::builder().build();
+	let world = &mut harness.app.world;
+	let at = LoopTime::from(1_234);
+	let curr = world.resource::<TimeLoop>().curr.0;
+	moment_at(world, curr, at).happenings = vec![labelled("a"), labelled("b")];
+	moment_at(world, curr, at).happenings[1].disabled = true;
+	let save = SaveFile::capture(world).expect("the loop should be in a loaded timeline");
+
+	// The file gained a group in front of the others since the save.
+	moment_at(world, curr, at).happenings = vec![labelled("new"), labelled("a"), labelled("b")];
+	save.restore(world);
+
+	let disabled = moment_at(world, curr, at)
+		.happenings
+		.iter()
+		.map(|h| h.disabled)
+		.collect::<Vec<_>>();
+	assert_eq!(disabled, [false, false, true]);
+}
@@ -0,0 +1,579 @@
+#![cfg(feature = "testing")]
+
+use bevy::{asset::AssetPath, ecs::system::Command, prelude::*};
+use bevy_xpbd_3d::prelude::{CollidingEntities, RigidBody};
+use kairoi::{
+	data::{
+		area::{ActiveArea, AreaId},
+		dlg::ActiveDialogue,
+		flags::{Flag, FlagValue, WorldFlags},
+		tl::{
+			Activator, Do, DoList, Happenings, Log, LoopTime, Moment, MomentRef, TPath, TimeLoop,
+			TimeScale, Timeline, Trigger, TriggerKind, T,
+		},
+		RegisterNamedSystem, Str,
+	},
+	happens::{
+		IncrementFlag, ModifyTimeline, MomentUpdate, ResetLoop, RunSystem, SetDisabled, SetFlag,
+		SpawnTrigger, TimelineCommand,
+	},
+	scn::{
+		intro::{HackablePanel, OpenPanel},
+		Resettable,
+	},
+	testing::TimeGraphHarness,
+	time_graph::{
+		analysis::{GraphError, TimeGraph},
+		interact_score,
+		reload::TimelineReloadPolicy,
+		transition::{start_transition, LoopTransition, LoopTransitions},
+	},
+	GameState,
+};
+use std::time::Duration;
+
+fn secs(s: i64) -> LoopTime {
+	LoopTime::from(s * 1000)
+}
+
+#[test]
+fn intro_inherits_area_1_before_branching() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(21), 1_000);
+
+	let area_1 = harness.timeline_id("tl/area_1.tl.ron");
+	let intro = harness.timeline_id("tl/intro.tl.ron");
+	let applied = harness.applied();
+	assert_eq!(applied.len(), 2, "{applied:#?}");
+
+	assert_eq!(applied[0].timeline, area_1);
+	assert_eq!(applied[0].at, LoopTime::from(500));
+	assert_eq!(
+		applied[0].action.reflect_type_path(),
+		"kairoi::scn::intro::RaiseWalls"
+	);
+
+	assert_eq!(applied[1].timeline, intro);
+	assert_eq!(applied[1].at, secs(20));
+	assert_eq!(
+		applied[1].action.reflect_type_path(),
+		"happens::SpawnTrigger"
+	);
+}
+
+#[test]
+fn area_1_does_not_run_intro_happenings() {
+	let mut harness = TimeGraphHarness::builder()
+		.start_at(TPath("tl/area_1.tl.ron".into(), LoopTime::EPOCH))
+		.build();
+	harness.run_until(secs(30), 1_000);
+
+	assert_eq!(
+		harness.applied_type_paths(),
+		["kairoi::scn::intro::RaiseWalls", "happens::SpawnTrigger"]
+	);
+	let area_1 = harness.timeline_id("tl/area_1.tl.ron");
+	assert!(harness.applied().iter().all(|it| it.timeline == area_1));
+}
+
+#[test]
+fn happenings_are_not_repeated_across_frames() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(1), 1_000);
+	assert_eq!(harness.applied().len(), 1);
+	harness.clear_applied();
+	harness.run_until(secs(19), 1_000);
+	assert!(harness.applied().is_empty(), "{:#?}", harness.applied());
+}
+
+#[test]
+fn shipped_time_graph_is_valid() {
+	let harness = TimeGraphHarness::builder().build();
+	let timelines = harness.app.world.resource::<Assets<Timeline>>();
+	let graph = TimeGraph::new(timelines, harness.app.world.resource::<AssetServer>());
+	let area_1 = harness.timeline_id("tl/area_1.tl.ron");
+	let intro = harness.timeline_id("tl/intro.tl.ron");
+	let errors = graph.validate_from(area_1);
+	assert!(errors.is_empty(), "{errors:#?}");
+	assert!(graph.check_branch(T(intro, secs(20)), area_1).is_ok());
+}
+
+#[test]
+fn branches_are_only_reachable_before_they_split_off() {
+	let harness = TimeGraphHarness::builder().build();
+	let timelines = harness.app.world.resource::<Assets<Timeline>>();
+	let graph = TimeGraph::new(timelines, harness.app.world.resource::<AssetServer>());
+	let area_1 = harness.timeline_id("tl/area_1.tl.ron");
+	let intro = harness.timeline_id("tl/intro.tl.ron");
+	assert!(graph.check_branch(T(area_1, secs(10)), intro).is_ok());
+	let Err(e) = graph.check_branch(T(area_1, secs(20)), intro) else {
+		panic!("intro branches off at 19s, so it can't be reached at 20s");
+	};
+	assert!(matches!(e, GraphError::Unreachable { .. }), "{e}");
+	assert!(e.to_string().contains("tl/intro.tl.ron"), "{e}");
+}
+
+#[test]
+fn rewinding_undoes_reversible_happenings() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(21), 1_000);
+	harness.clear_applied();
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+	assert_eq!(harness.now(), LoopTime::EPOCH);
+
+	let applied = harness.applied();
+	assert_eq!(applied.len(), 1, "{applied:#?}");
+	assert!(applied[0].undone);
+	assert_eq!(applied[0].timeline, harness.timeline_id("tl/area_1.tl.ron"));
+	assert_eq!(applied[0].at, LoopTime::from(500));
+	assert_eq!(
+		applied[0].action.reflect_type_path(),
+		"kairoi::scn::intro::RaiseWalls"
+	);
+}
+
+#[test]
+fn only_persistent_flags_survive_rewinding() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(1), 1_000);
+	let mut flags = harness.app.world.resource_mut::<WorldFlags>();
+	flags.insert(
+		"learned_code".into(),
+		Flag {
+			value: FlagValue::Int(1234),
+			persistent: true,
+		},
+	);
+	flags.insert(
+		"lever_flipped".into(),
+		Flag {
+			value: FlagValue::Bool(true),
+			persistent: false,
+		},
+	);
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+
+	let flags = harness.app.world.resource::<WorldFlags>();
+	assert_eq!(flags.value("learned_code"), Some(FlagValue::Int(1234)));
+	assert_eq!(flags.value("lever_flipped"), None);
+}
+
+#[derive(Resource, Default)]
+struct Runs(u32);
+
+fn run_system_at(at: LoopTime, name: &str) -> (LoopTime, Moment) {
+	let moment = Moment {
+		happenings: vec![Happenings {
+			actions: vec![Box::new(RunSystem(name.into()))],
+			..default()
+		}],
+		..default()
+	};
+	(at, moment)
+}
+
+#[test]
+fn timelines_run_named_systems() {
+	let mut harness = TimeGraphHarness::builder().dry_run(false).build();
+	harness
+		.app
+		.init_resource::<Runs>()
+		.register_named_system("count_runs", |mut runs: ResMut<Runs>| runs.0 += 1);
+	let curr = harness.app.world.resource::<TimeLoop>().curr.0;
+	let mut assets = harness.app.world.resource_mut::<Assets<Timeline>>();
+	let tl = assets
+		.get_mut(curr)
+		.expect("current timeline should be loaded");
+	tl.moments.extend([
+		run_system_at(LoopTime::from(1_100), "count_runs"),
+		// Only logs an error.
+		run_system_at(LoopTime::from(1_200), "not_registered"),
+	]);
+	harness.run_until(LoopTime::from(1_500), 1_000);
+
+	assert_eq!(harness.app.world.resource::<Runs>().0, 1);
+	assert_eq!(
+		harness.applied_type_paths(),
+		[
+			"kairoi::scn::intro::RaiseWalls",
+			"happens::RunSystem",
+			"happens::RunSystem"
+		]
+	);
+}
+
+#[test]
+fn incrementing_keeps_a_flags_persistence() {
+	let mut harness = TimeGraphHarness::builder().build();
+	let world = &mut harness.app.world;
+	SetFlag {
+		flag: "deaths".into(),
+		value: FlagValue::Int(0),
+		persistent: true,
+	}
+	.apply(world);
+	IncrementFlag {
+		flag: "deaths".into(),
+		by: 1,
+		persistent: false,
+	}
+	.apply(world);
+	IncrementFlag {
+		flag: "levers".into(),
+		by: 1,
+		persistent: false,
+	}
+	.apply(world);
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+
+	let flags = harness.app.world.resource::<WorldFlags>();
+	assert_eq!(flags.value("deaths"), Some(FlagValue::Int(1)));
+	assert_eq!(flags.value("levers"), None);
+}
+
+#[test]
+fn rewinding_closes_dialogue() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(1), 1_000);
+	harness.app.world.insert_resource(ActiveDialogue {
+		path: AssetPath::from("clock.dlg.ron"),
+		handle: default(),
+		start: None,
+		line: Some(0),
+		revealed: 0.0,
+		seen: false,
+	});
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+
+	assert!(!harness.app.world.contains_resource::<ActiveDialogue>());
+}
+
+#[test]
+fn hot_reload_keeps_edits_and_reruns_added_moments() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness
+		.app
+		.world
+		.resource_mut::<TimelineReloadPolicy>()
+		.rerun_added = true;
+	harness.run_until(secs(5), 1_000);
+	harness.clear_applied();
+
+	ModifyTimeline(vec![TimelineCommand {
+		path: "tl/intro.tl.ron".into(),
+		updates: vec![MomentUpdate {
+			moment: MomentRef::Labelled("spawn_reset_trigger".into()),
+			disabled: Some(SetDisabled::Set { disabled: true }),
+			happenings: default(),
+		}],
+	}])
+	.apply(&mut harness.app.world);
+	harness.update();
+
+	// Stand-in for the file changing on disk, which would bring back the file's `disabled` flag.
+	let intro = harness.timeline_id("tl/intro.tl.ron");
+	let mut assets = harness.app.world.resource_mut::<Assets<Timeline>>();
+	let tl = assets.get_mut(intro).expect("intro should be loaded");
+	tl.moments.insert(
+		secs(2),
+		Moment {
+			label: Some("added".into()),
+			happenings: vec![Happenings {
+				actions: vec![Box::new(Log {
+					level: default(),
+					msg: "added".into(),
+				})],
+				..default()
+			}],
+			..default()
+		},
+	);
+	for mom in tl.moments.values_mut() {
+		mom.disabled = false;
+	}
+	harness.update();
+	harness.update();
+
+	assert_eq!(harness.applied_type_paths(), ["happens::Log"]);
+	assert_eq!(harness.applied()[0].at, secs(2));
+	let assets = harness.app.world.resource::<Assets<Timeline>>();
+	let tl = assets.get(intro).expect("intro should be loaded");
+	assert!(tl.moments[&secs(20)].disabled);
+}
+
+#[test]
+fn time_scale_changes_how_fast_the_loop_runs() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.update();
+	harness.app.world.resource_mut::<TimeScale>().global = 2.0;
+
+	let before = harness.now();
+	harness.update();
+	assert_eq!(harness.now() - before, LoopTime::from(200));
+	// Only the loop is scaled, not the rest of the game.
+	assert_eq!(
+		harness
+			.app
+			.world
+			.resource::<Time<Virtual>>()
+			.relative_speed(),
+		1.0
+	);
+}
+
+#[test]
+fn instant_transition_finishes_within_a_frame() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(1), 1_000);
+	start_transition(
+		&mut harness.app.world,
+		LoopTime::EPOCH,
+		Some("instant".into()),
+	);
+	// One frame to enter `ResettingLoop` and seek, one to get back to `Running`.
+	harness.update();
+	harness.update();
+
+	assert_eq!(harness.now(), LoopTime::EPOCH);
+	assert_eq!(
+		*harness.app.world.resource::<State<GameState>>(),
+		GameState::Running
+	);
+}
+
+#[test]
+fn transition_effects_peak_at_the_reset() {
+	let transition = LoopTransition {
+		reset_at: 0.25,
+		..default()
+	};
+	assert_eq!(transition.effect_strength(0.0), 0.0);
+	assert_eq!(transition.effect_strength(0.25), 1.0);
+	assert_eq!(transition.effect_strength(1.0), 0.0);
+	// Closer to the peak, so it has faded in further than it has faded out by the end.
+	assert!(transition.effect_strength(0.01) > transition.effect_strength(0.99));
+}
+
+#[test]
+fn unknown_transition_names_are_found() {
+	let reset = |transition: &str| -> Box<dyn Do> {
+		Box::new(ResetLoop {
+			to: LoopTime::EPOCH,
+			transition: Some(transition.into()),
+		})
+	};
+	let mut timeline = Timeline::default();
+	timeline.moments.insert(
+		secs(1),
+		Moment {
+			happenings: vec![Happenings {
+				actions: vec![
+					reset("instant"),
+					reset("typo"),
+					Box::new(SpawnTrigger {
+						trigger: Trigger {
+							causes: DoList(vec![reset("also_typo")]),
+							..default()
+						},
+						..default()
+					}),
+				],
+				..default()
+			}],
+			..default()
+		},
+	);
+
+	let unknown = LoopTransitions::default().unknown_in(&timeline);
+	assert_eq!(
+		unknown,
+		[
+			(secs(1), Str::from("typo")),
+			(secs(1), Str::from("also_typo"))
+		]
+	);
+}
+
+#[test]
+fn interact_focus_prefers_near_triggers_in_front() {
+	let facing = Vec2::Y;
+	let ahead = interact_score(Vec2::ZERO, facing, Vec2::new(0.0, 1.0));
+	let behind = interact_score(Vec2::ZERO, facing, Vec2::new(0.0, -1.0));
+	let far_ahead = interact_score(Vec2::ZERO, facing, Vec2::new(0.0, 2.0));
+	let beside = interact_score(Vec2::ZERO, facing, Vec2::new(1.0, 0.0));
+	assert!(ahead < beside, "{ahead} {beside}");
+	assert!(beside < behind, "{beside} {behind}");
+	assert!(far_ahead < behind, "{far_ahead} {behind}");
+	assert!(ahead < far_ahead, "{ahead} {far_ahead}");
+}
+
+fn count_trigger(kind: TriggerKind, flag: &str) -> Trigger {
+	Trigger {
+		causes: DoList(vec![Box::new(IncrementFlag {
+			flag: flag.into(),
+			by: 1,
+			persistent: false,
+		})]),
+		kind,
+		by: Activator::Named("Crate".into()),
+		..default()
+	}
+}
+
+#[test]
+fn sensor_triggers_fire_on_edges_and_cooldowns() {
+	let mut harness = TimeGraphHarness::builder().build();
+	let world = &mut harness.app.world;
+	let crate_id = world.spawn(Name::new("Crate")).id();
+	let triggers = [
+		world
+			.spawn(count_trigger(TriggerKind::Enter, "entered"))
+			.id(),
+		world.spawn(count_trigger(TriggerKind::Exit, "exited")).id(),
+		world
+			.spawn(count_trigger(
+				TriggerKind::Cooldown { period: secs(1) },
+				"cooldown",
+			))
+			.id(),
+		world
+			.spawn(count_trigger(
+				TriggerKind::Stay {
+					after: LoopTime::from(500),
+				},
+				"stayed",
+			))
+			.id(),
+	];
+	// Gives the triggers somewhere to keep track of what is touching them.
+	harness.update();
+
+	for id in triggers {
+		harness
+			.app
+			.world
+			.get_mut::<CollidingEntities>(id)
+			.expect("triggers should have `CollidingEntities`")
+			.insert(crate_id);
+	}
+	// 1.5s at the default 100ms step.
+	for _ in 0..15 {
+		harness.update();
+	}
+	for id in triggers {
+		harness
+			.app
+			.world
+			.get_mut::<CollidingEntities>(id)
+			.expect("triggers should have `CollidingEntities`")
+			.clear();
+	}
+	harness.update();
+	harness.update();
+
+	let flags = harness.app.world.resource::<WorldFlags>();
+	assert_eq!(flags.value("entered"), Some(FlagValue::Int(1)));
+	assert_eq!(flags.value("exited"), Some(FlagValue::Int(1)));
+	assert_eq!(flags.value("cooldown"), Some(FlagValue::Int(2)));
+	assert_eq!(flags.value("stayed"), Some(FlagValue::Int(1)));
+}
+
+#[test]
+fn any_activator_ignores_static_colliders() {
+	let mut harness = TimeGraphHarness::builder().build();
+	let world = &mut harness.app.world;
+	let floor = world.spawn(RigidBody::Static).id();
+	let ball = world.spawn(RigidBody::Dynamic).id();
+	let trigger = Trigger {
+		by: Activator::Any,
+		..count_trigger(TriggerKind::Enter, "entered")
+	};
+	let trigger = world.spawn(trigger).id();
+	harness.update();
+
+	let mut colliding = harness
+		.app
+		.world
+		.get_mut::<CollidingEntities>(trigger)
+		.expect("triggers should have `CollidingEntities`");
+	colliding.insert(floor);
+	harness.update();
+	let flags = harness.app.world.resource::<WorldFlags>();
+	assert_eq!(flags.value("entered"), None);
+
+	harness
+		.app
+		.world
+		.get_mut::<CollidingEntities>(trigger)
+		.expect("triggers should have `CollidingEntities`")
+		.insert(ball);
+	harness.update();
+	let flags = harness.app.world.resource::<WorldFlags>();
+	assert_eq!(flags.value("entered"), Some(FlagValue::Int(1)));
+}
+
+#[test]
+fn reset_only_touches_active_area() {
+	let mut harness = TimeGraphHarness::builder().build();
+	harness.run_until(secs(1), 1_000);
+	let world = &mut harness.app.world;
+	world.insert_resource(ActiveArea(Some("here".into())));
+	let global = world.spawn(Resettable::default()).id();
+	let here = world
+		.spawn((Resettable::default(), AreaId("here".into())))
+		.id();
+	let here_scenery = world.spawn(AreaId("here".into())).id();
+	let elsewhere = world
+		.spawn((Resettable::default(), AreaId("elsewhere".into())))
+		.id();
+
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+
+	let world = &harness.app.world;
+	assert!(world.get_entity(global).is_none());
+	assert!(world.get_entity(here).is_none());
+	// Reloaded along with the rest of the area's scene.
+	assert!(world.get_entity(here_scenery).is_none());
+	assert!(world.get_entity(elsewhere).is_some());
+}
+
+/// Runs frames until the intro area's scene has spawned its panel.
+fn wait_for_panel(harness: &mut TimeGraphHarness) -> Entity {
+	for _ in 0..1_000 {
+		let mut q = harness
+			.app
+			.world
+			.query_filtered::<Entity, With<HackablePanel>>();
+		if let Ok(id) = q.get_single(&harness.app.world) {
+			return id;
+		}
+		harness.update();
+	}
+	panic!("the intro area's panel never spawned");
+}
+
+#[test]
+fn rewinding_respawns_the_active_area() {
+	let mut harness = TimeGraphHarness::builder().with_areas().build();
+	let before = wait_for_panel(&mut harness);
+	harness.run_until(harness.now() + Duration::from_secs(1), 1_000);
+	harness.rewind_to(LoopTime::EPOCH, 1_000);
+
+	let after = wait_for_panel(&mut harness);
+	assert_ne!(
+		before, after,
+		"the panel should have been reloaded from the scene"
+	);
+	let world = &mut harness.app.world;
+	let mut triggers = world.query::<&Trigger>();
+	let opens_panel = triggers.iter(world).any(|trigger| {
+		trigger
+			.causes
+			.iter()
+			.any(|cause| cause.as_reflect().is::<OpenPanel>())
+	});
+	assert!(
+		opens_panel,
+		"the panel's trigger should have been reloaded too"
+	);
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "testing")]
+
+use bevy::{
+	asset::AssetPath,
+	prelude::*,
+	reflect::{serde::TypedReflectSerializer, TypeRegistry},
+	utils::HashMap,
+};
+use kairoi::{
+	conditions::{EntityExists, InTimeline},
+	data::{
+		anim::AnimationClipDef,
+		area::AreaIndex,
+		dlg::Dialogue,
+		tl::{
+			Happenings, Log, LogLevel, LoopTime, Moment, Timeline, TimelineDeserializer,
+			TimelineSerializer, T,
+		},
+		LoadStdMat, Str,
+	},
+	testing::TimeGraphHarness,
+};
+use serde::de::DeserializeSeed;
+
+/// Serializes `timeline`, parses the output back and checks it matches the original.
+fn round_trip(harness: &TimeGraphHarness, timeline: &Timeline) {
+	let registry = harness.app.world.resource::<AppTypeRegistry>().read();
+	let asset_server = harness.app.world.resource::<AssetServer>();
+	let ron = TimelineSerializer {
+		timeline,
+		registry: &registry,
+		asset_server,
+	}
+	.to_ron_string()
+	.expect("timeline should serialize");
+	let mut de = ron::de::Deserializer::from_str(&ron).expect("output should be valid RON");
+	let parsed = TimelineDeserializer {
+		registry: &registry,
+		asset_server,
+	}
+	.deserialize(&mut de)
+	.unwrap_or_else(|e| panic!("{}\n{ron}", de.span_error(e)));
+
+	let times = |tl: &Timeline| tl.moments.keys().copied().collect::<Vec<_>>();
+	assert_eq!(times(&parsed), times(timeline), "{ron}");
+	let links = |tl: &Timeline| {
+		let link = |t: Option<T>| t.map(|T(id, at)| (id, at));
+		(link(tl.branch_from), link(tl.merge_into))
+	};
+	assert_eq!(links(&parsed), links(timeline), "{ron}");
+	for ((at, original), parsed) in timeline.moments.iter().zip(parsed.moments.values()) {
+		assert_eq!(parsed.label, original.label, "moment at {at}\n{ron}");
+		assert_eq!(parsed.desc, original.desc, "moment at {at}\n{ron}");
+		assert_eq!(parsed.disabled, original.disabled, "moment at {at}\n{ron}");
+		assert_eq!(
+			parsed.happenings.len(),
+			original.happenings.len(),
+			"moment at {at}\n{ron}"
+		);
+		for (original, parsed) in original.happenings.iter().zip(&parsed.happenings) {
+			assert_eq!(parsed.label, original.label, "moment at {at}\n{ron}");
+			assert_eq!(parsed.disabled, original.disabled, "moment at {at}\n{ron}");
+			let actions = |h: &Happenings| {
+				h.actions
+					.iter()
+					.map(|a| reflected(&registry, a.as_reflect()))
+					.collect::<Vec<_>>()
+			};
+			assert_eq!(actions(parsed), actions(original), "moment at {at}");
+			let conditions = |h: &Happenings| {
+				let when = h.when.iter().map(|c| reflected(&registry, c.as_reflect()));
+				let unless = h
+					.unless
+					.iter()
+					.map(|c| reflected(&registry, c.as_reflect()));
+				(when.collect::<Vec<_>>(), unless.collect::<Vec<_>>())
+			};
+			assert_eq!(conditions(parsed), conditions(original), "moment at {at}");
+		}
+	}
+}
+
+/// A reflected value's type path and its fields, written out so they can be compared.
+fn reflected(registry: &TypeRegistry, value: &dyn Reflect) -> (String, String) {
+	let fields = ron::to_string(&TypedReflectSerializer::new(value, registry))
+		.unwrap_or_else(|e| panic!("{}: {e}", value.reflect_type_path()));
+	(value.reflect_type_path().to_owned(), fields)
+}
+
+#[test]
+fn shipped_timelines_round_trip() {
+	let harness = TimeGraphHarness::builder().build();
+	let timelines = harness.app.world.resource::<Assets<Timeline>>();
+	for path in ["tl/intro.tl.ron", "tl/area_1.tl.ron"] {
+		let tl = timelines
+			.get(harness.timeline_id(path))
+			.expect("timeline should be loaded");
+		round_trip(&harness, tl);
+	}
+}
+
+/// Tiny deterministic PRNG so the test doesn't need extra dependencies.
+struct Lcg(u64);
+
+impl Lcg {
+	fn next(&mut self) -> u64 {
+		self.0 = self
+			.0
+			.wrapping_mul(6364136223846793005)
+			.wrapping_add(1442695040888963407);
+		self.0 >> 33
+	}
+
+	fn below(&mut self, n: u64) -> u64 {
+		self.next() % n
+	}
+}
+
+fn random_timeline(rng: &mut Lcg) -> Timeline {
+	let mut timeline = Timeline::default();
+	for _ in 0..rng.below(8) {
+		let at = LoopTime::from(rng.below(120_000) as i64 - 10_000);
+		let happenings = (0..rng.below(4))
+			.map(|i| Happenings {
+				label: (rng.below(2) == 0).then(|| (&*format!("group_{i}")).into()),
+				actions: (0..rng.below(3))
+					.map(|j| {
+						Box::new(Log {
+							level: match rng.below(5) {
+								0 => LogLevel::Trace,
+								1 => LogLevel::Debug,
+								2 => LogLevel::Info,
+								3 => LogLevel::Warn,
+								_ => LogLevel::Error,
+							},
+							msg: format!("message {j} \"quoted\"").into(),
+						}) as _
+					})
+					.collect(),
+				disabled: rng.below(4) == 0,
+				when: (0..rng.below(2))
+					.map(|_| Box::new(InTimeline("tl/intro.tl.ron".into())) as _)
+					.collect(),
+				unless: (0..rng.below(2))
+					.map(|j| Box::new(EntityExists(Name::new(format!("entity_{j}")))) as _)
+					.collect(),
+			})
+			.collect();
+		timeline.moments.insert(
+			at,
+			Moment {
+				label: (rng.below(2) == 0).then(|| (&*format!("moment_{}", at.millis())).into()),
+				desc: (rng.below(3) == 0).then(|| "some description".into()),
+				happenings,
+				disabled: rng.below(5) == 0,
+			},
+		);
+	}
+	timeline
+}
+
+#[test]
+fn random_timelines_round_trip() {
+	let harness = TimeGraphHarness::builder().build();
+	let mut rng = Lcg(0x6b61_6972_6f69);
+	for _ in 0..64 {
+		let timeline = random_timeline(&mut rng);
+		round_trip(&harness, &timeline);
+	}
+}
+
+#[test]
+fn dialogue_lines_depend_on_timeline() {
+	// Sets up the type registry that `Choice::causes` deserializes with.
+	let _harness = TimeGraphHarness::builder().build();
+	let dlg = ron::from_str::<Dialogue>(
+		r#"[
+			(speaker: Some("Clock"), text: "Tick."),
+			(text: "Only in the intro.", timelines: ["tl/intro.tl.ron"]),
+			(
+				label: Some("ask"),
+				text: "Again?",
+				choices: [
+					(
+						text: "Yes",
+						causes: {
+							"happens::Log": (msg: "again"),
+						},
+						goto: Some("ask"),
+					),
+					(text: "No"),
+				],
+			),
+		]"#,
+	)
+	.expect("dialogue should deserialize");
+
+	let intro = AssetPath::from("tl/intro.tl.ron");
+	let area_1 = AssetPath::from("tl/area_1.tl.ron");
+	assert_eq!(dlg.next_line(1, Some(&intro)), Some(1));
+	assert_eq!(dlg.next_line(1, Some(&area_1)), Some(2));
+	assert_eq!(dlg.next_line(1, None), Some(2));
+	assert_eq!(dlg.find_label("ask".into(), Some(&intro)), Some(2));
+	assert_eq!(dlg.next_line(3, Some(&intro)), None);
+
+	assert_eq!(dlg.line_key(0), Str::from("#0"));
+	assert_eq!(dlg.line_key(2), Str::from("ask"));
+	let choices = &dlg.lines[2].choices;
+	assert_eq!(choices[0].causes.len(), 1);
+	assert!(choices[1].causes.is_empty());
+	assert_eq!(choices[1].goto, None);
+}
+
+fn read_asset(path: &str) -> String {
+	let path = format!("{}/assets/{path}", env!("CARGO_MANIFEST_DIR"));
+	std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+#[test]
+fn shipped_scene_data_parses() {
+	let mats = ron::from_str::<HashMap<Str, LoadStdMat>>(&read_asset("world.mats.ron"))
+		.expect("materials should parse");
+	assert!(mats.contains_key(&Str::from("dark_gray")));
+
+	let areas = ron::from_str::<AreaIndex>(&read_asset("world.areas.ron"))
+		.expect("area index should parse");
+	assert!(areas.areas.contains_key(&Str::from("intro")));
+
+	let raise_walls =
+		ron::from_str::<AnimationClipDef>(&read_asset("scn/intro/raise_walls.anim.ron"))
+			.expect("animation should parse");
+	assert_eq!(AnimationClip::from(raise_walls).duration(), 0.5);
+}